@@ -0,0 +1,156 @@
+//! The in-process bank of solana-program-test that `soltx test` runs transaction files
+//! against, so that no validator is needed
+
+use {
+    crate::{
+        expect::Execution,
+        fixtures::Fixtures,
+        parse::SendOptions,
+        sender::{Attempt, Sender, Simulation},
+    },
+    anyhow::{anyhow, bail, Context, Result},
+    solana_program_test::{BanksClient, ProgramTest},
+    solana_sdk::{
+        account::Account, bpf_loader, hash::Hash, native_token::sol_to_lamports, nonce,
+        pubkey::Pubkey, rent::Rent, signature::Signature, system_program, transaction::Transaction,
+        transport::TransportError,
+    },
+    std::{fs, path::Path},
+    tokio::runtime::Runtime,
+};
+
+/// The balance of the signers that the bank funds, in SOL
+pub const FUNDED_SOL: f64 = 1000.0;
+
+pub struct BankSender {
+    runtime: Runtime,
+    banks_client: BanksClient,
+}
+
+impl BankSender {
+    /// Starts a bank with the programs and accounts of `fixtures`, whose files are
+    /// relative to `base_dir`. Each of `funded` that no fixture sets up is given
+    /// [`FUNDED_SOL`].
+    pub fn start(fixtures: &Fixtures, base_dir: &Path, funded: &[Pubkey]) -> Result<Self> {
+        let mut program_test = ProgramTest::default();
+        for program in &fixtures.programs {
+            let path = base_dir.join(&program.file);
+            let data = fs::read(&path)
+                .with_context(|| format!("failed to read program {}", path.display()))?;
+            program_test.add_account(
+                program.address,
+                Account {
+                    lamports: Rent::default().minimum_balance(data.len()).max(1),
+                    data,
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+        }
+        let accounts = fixtures.load_accounts(base_dir)?;
+        for (address, account) in &accounts {
+            program_test.add_account(*address, account.clone());
+        }
+        for pubkey in funded {
+            if !accounts.iter().any(|(address, _)| address == pubkey) {
+                program_test.add_account(
+                    *pubkey,
+                    Account::new(sol_to_lamports(FUNDED_SOL), 0, &system_program::id()),
+                );
+            }
+        }
+
+        let runtime = Runtime::new().context("failed to start the bank's runtime")?;
+        let (banks_client, _payer, _blockhash) = runtime.block_on(program_test.start());
+        Ok(Self {
+            runtime,
+            banks_client,
+        })
+    }
+}
+
+impl Sender for BankSender {
+    fn get_blockhash(
+        &mut self,
+        nonce_account: Option<&Pubkey>,
+        send_options: &SendOptions,
+    ) -> Result<Hash> {
+        let address = match nonce_account {
+            Some(address) => address,
+            None => {
+                return Ok(self
+                    .runtime
+                    .block_on(self.banks_client.get_recent_blockhash())?)
+            }
+        };
+        let account = self
+            .get_account(address, send_options)?
+            .ok_or_else(|| anyhow!("nonce account {} does not exist", address))?;
+        let versions = bincode::deserialize::<nonce::state::Versions>(&account.data)
+            .with_context(|| format!("{} is not a nonce account", address))?;
+        match versions.convert_to_current() {
+            nonce::State::Initialized(data) => Ok(data.blockhash),
+            nonce::State::Uninitialized => bail!("nonce account {} is not initialized", address),
+        }
+    }
+
+    /// Processes `transaction` right away, so there is nothing to retry
+    fn send(
+        &mut self,
+        transaction: &mut Transaction,
+        _send_options: &SendOptions,
+        _resign: &mut dyn FnMut(Hash) -> Result<Transaction>,
+        report: &mut dyn FnMut(&Attempt),
+    ) -> Result<Signature> {
+        report(&Attempt {
+            number: 1,
+            max_attempts: 1,
+            signature: transaction.signatures[0],
+            previous_error: None,
+            resigned: false,
+        });
+        self.runtime
+            .block_on(self.banks_client.process_transaction(transaction.clone()))?;
+        Ok(transaction.signatures[0])
+    }
+
+    fn simulate(
+        &mut self,
+        _transaction: &Transaction,
+        _send_options: &SendOptions,
+    ) -> Result<Simulation> {
+        bail!("the in-process bank cannot simulate transactions")
+    }
+
+    fn get_account(
+        &mut self,
+        address: &Pubkey,
+        _send_options: &SendOptions,
+    ) -> Result<Option<Account>> {
+        Ok(self
+            .runtime
+            .block_on(self.banks_client.get_account(*address))?)
+    }
+
+    /// The banks client of solana 1.6 does not return the logs of a transaction
+    fn returns_logs(&self) -> bool {
+        false
+    }
+
+    fn execution(
+        &mut self,
+        _transaction: &Transaction,
+        result: &Result<Signature>,
+        _send_options: &SendOptions,
+    ) -> Result<Option<Execution>> {
+        let err = match result {
+            Ok(_) => None,
+            Err(err) => match err.downcast_ref::<TransportError>() {
+                Some(TransportError::TransactionError(err)) => Some(err.clone()),
+                _ => return Ok(None),
+            },
+        };
+        Ok(Some(Execution { err, logs: vec![] }))
+    }
+}
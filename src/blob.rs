@@ -0,0 +1,193 @@
+use {
+    anyhow::{anyhow, bail, Context, Result},
+    solana_sdk::{
+        hash::Hash, instruction::CompiledInstruction, message::Message, pubkey::Pubkey,
+        sanitize::Sanitize, signature::Signature, transaction::Transaction,
+    },
+    solana_transaction_status::{
+        EncodedTransaction, UiMessage, UiTransaction, UiTransactionEncoding,
+    },
+    std::{fs, str::FromStr},
+};
+
+pub const ENCODINGS: &[&str] = &["base64", "base58", "json"];
+
+/// How a serialized transaction is written out.
+///
+/// `base64` and `base58` encode the bincode wire format that the RPC `sendTransaction`
+/// method takes, `json` is the raw message format of `getConfirmedTransaction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Base64,
+    Base58,
+    Json,
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "base64" => Ok(Encoding::Base64),
+            "base58" => Ok(Encoding::Base58),
+            "json" => Ok(Encoding::Json),
+            _ => bail!(
+                "unknown encoding {}, expected one of {}",
+                s,
+                ENCODINGS.join(", ")
+            ),
+        }
+    }
+}
+
+/// Serializes `transaction` on a single line, unsigned signatures included
+pub fn encode(transaction: &Transaction, encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Base64 => Ok(base64::encode(bincode::serialize(transaction)?)),
+        Encoding::Base58 => Ok(bs58::encode(bincode::serialize(transaction)?).into_string()),
+        // untagged, so this serializes as the inner `UiTransaction`
+        Encoding::Json => Ok(serde_json::to_string(&EncodedTransaction::encode(
+            transaction.clone(),
+            UiTransactionEncoding::Json,
+        ))?),
+    }
+}
+
+/// Deserializes a transaction written by `encode`, detecting its encoding
+pub fn decode(blob: &str) -> Result<(Transaction, Encoding)> {
+    let blob = blob.trim();
+    if blob.starts_with('{') {
+        let transaction =
+            serde_json::from_str::<UiTransaction>(blob).context("invalid json transaction")?;
+        let transaction = from_ui_transaction(transaction)?;
+        transaction
+            .sanitize()
+            .map_err(|err| anyhow!("invalid transaction: {:?}", err))?;
+        return Ok((transaction, Encoding::Json));
+    }
+    // the base58 alphabet is a subset of base64's, so base64 is tried first. A base58
+    // blob is practically never valid base64 that also deserializes to a transaction.
+    let candidates = [
+        (base64::decode(blob).ok(), Encoding::Base64),
+        (bs58::decode(blob).into_vec().ok(), Encoding::Base58),
+    ];
+    for (bytes, encoding) in candidates.iter() {
+        if let Some(transaction) = bytes
+            .as_ref()
+            .and_then(|bytes| bincode::deserialize::<Transaction>(bytes).ok())
+            .filter(|transaction| transaction.sanitize().is_ok())
+        {
+            return Ok((transaction, *encoding));
+        }
+    }
+    bail!("not a base64, base58 or json encoded transaction")
+}
+
+/// Reads the blobs passed on the command line. A value that names a file is replaced by
+/// the blobs in the file, one per line, `-` reads them from stdin.
+pub fn read_blobs<'a>(values: impl IntoIterator<Item = &'a str>) -> Result<Vec<String>> {
+    let mut blobs = vec![];
+    for value in values {
+        let content = if value == "-" {
+            let mut content = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)?;
+            content
+        } else if fs::metadata(value).map(|m| m.is_file()).unwrap_or_default() {
+            fs::read_to_string(value).with_context(|| format!("failed to read {}", value))?
+        } else {
+            blobs.push(value.to_string());
+            continue;
+        };
+        blobs.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string),
+        );
+    }
+    Ok(blobs)
+}
+
+fn from_ui_transaction(transaction: UiTransaction) -> Result<Transaction> {
+    let message = match transaction.message {
+        UiMessage::Raw(message) => message,
+        UiMessage::Parsed(_) => bail!("parsed json transactions are not supported"),
+    };
+    let account_keys = message
+        .account_keys
+        .iter()
+        .map(|key| Pubkey::from_str(key).map_err(|_| anyhow!("invalid account key {}", key)))
+        .collect::<Result<_>>()?;
+    let instructions = message
+        .instructions
+        .into_iter()
+        .map(|instruction| {
+            Ok(CompiledInstruction {
+                program_id_index: instruction.program_id_index,
+                accounts: instruction.accounts,
+                data: bs58::decode(&instruction.data)
+                    .into_vec()
+                    .map_err(|err| anyhow!("invalid instruction data: {}", err))?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let signatures = transaction
+        .signatures
+        .iter()
+        .map(|signature| {
+            Signature::from_str(signature).map_err(|_| anyhow!("invalid signature {}", signature))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Transaction {
+        signatures,
+        message: Message {
+            header: message.header,
+            account_keys,
+            recent_blockhash: Hash::from_str(&message.recent_blockhash)
+                .map_err(|_| anyhow!("invalid blockhash {}", message.recent_blockhash))?,
+            instructions,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            signature::{Keypair, Signer},
+            system_instruction,
+        },
+    };
+
+    #[test]
+    fn round_trip() {
+        let payer = Keypair::new();
+        let to = Pubkey::new_unique();
+        let message = Message::new(
+            &[system_instruction::transfer(&payer.pubkey(), &to, 42)],
+            Some(&payer.pubkey()),
+        );
+        let mut transaction = Transaction::new_unsigned(message);
+        let unsigned = transaction.clone();
+        transaction.sign(&[&payer], Hash::new_unique());
+        for transaction in &[unsigned, transaction] {
+            for encoding in &[Encoding::Base64, Encoding::Base58, Encoding::Json] {
+                let blob = encode(transaction, *encoding).unwrap();
+                assert!(!blob.contains('\n'));
+                let (decoded, detected) = decode(&blob).unwrap();
+                assert_eq!(&decoded, transaction);
+                assert_eq!(detected, *encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_blobs() {
+        assert!(decode("not a transaction").is_err());
+        assert!(decode("{}").is_err());
+        assert_eq!("base58".parse::<Encoding>().unwrap(), Encoding::Base58);
+        assert!("hex".parse::<Encoding>().is_err());
+    }
+}
@@ -0,0 +1,398 @@
+//! Turns parsed documents into signed transactions.
+//!
+//! [`build_transaction`] runs the whole flow for a document: it resolves the signer
+//! sources, fee payer and nonce, sets the compute budget, then compiles and signs the
+//! message. The functions it is made of are public for callers that need only a part.
+
+use {
+    crate::{
+        compute_budget,
+        parse::{ComputeBudget, ComputeUnitLimit, NonceSpec, SendOptions},
+        sender::{self, Sender},
+        Document,
+    },
+    anyhow::{bail, Context, Result},
+    solana_sdk::{
+        hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        pubkey::Pubkey,
+        signature::{NullSigner, Signature, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    std::collections::BTreeMap,
+};
+
+/// Loads a signer from its signer source
+type Load<'a> = Box<dyn FnMut(&str) -> Result<Box<dyn Signer>> + 'a>;
+
+/// Gives the pubkey of a signer source, or of the default signer for `None`
+pub type ResolvePubkey<'a> = dyn FnMut(Option<&str>) -> Result<Pubkey> + 'a;
+
+/// The signers of a run. The first is the default signer, which pays the fees and
+/// authorizes the nonce unless a document names another, and `load` loads any other
+/// signer from its signer source.
+///
+/// Each source is loaded at most once per run, so a `prompt://` or hardware wallet source
+/// shared by several documents asks only once.
+pub struct Signers<'a> {
+    signers: Vec<Box<dyn Signer>>,
+    load: Load<'a>,
+    /// The pubkey each source resolved to
+    sources: BTreeMap<String, Pubkey>,
+}
+
+impl<'a> Signers<'a> {
+    /// `signers` must hold at least the default signer
+    pub fn new(
+        signers: Vec<Box<dyn Signer>>,
+        load: impl FnMut(&str) -> Result<Box<dyn Signer>> + 'a,
+    ) -> Self {
+        assert!(!signers.is_empty(), "the default signer is missing");
+        Signers {
+            signers,
+            load: Box::new(load),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    pub fn default_pubkey(&self) -> Pubkey {
+        self.signers[0].pubkey()
+    }
+
+    pub fn signers(&self) -> &[Box<dyn Signer>] {
+        &self.signers
+    }
+
+    pub fn pubkeys(&self) -> Vec<Pubkey> {
+        self.signers.iter().map(|signer| signer.pubkey()).collect()
+    }
+
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.signers.iter().any(|signer| signer.pubkey() == *pubkey)
+    }
+
+    /// Loads the signer of `source` and adds it unless a signer for the same pubkey is
+    /// already present. Returns the pubkey of the signer. A source that was resolved
+    /// before, or that is the pubkey of a signer already present, is not loaded again.
+    pub fn resolve(&mut self, source: &str) -> Result<Pubkey> {
+        if let Some(pubkey) = self.sources.get(source) {
+            return Ok(*pubkey);
+        }
+        let pubkey = match source.parse::<Pubkey>() {
+            Ok(pubkey) if self.contains(&pubkey) => pubkey,
+            _ => {
+                let signer = (self.load)(source)?;
+                let pubkey = signer.pubkey();
+                if !self.contains(&pubkey) {
+                    self.signers.push(signer);
+                }
+                pubkey
+            }
+        };
+        self.sources.insert(source.to_string(), pubkey);
+        Ok(pubkey)
+    }
+
+    /// Resolves `source` like [`Signers::resolve`], or the default signer for `None`
+    fn resolve_or_default(&mut self, source: Option<&str>) -> Result<Pubkey> {
+        match source {
+            Some(source) => self.resolve(source),
+            None => Ok(self.default_pubkey()),
+        }
+    }
+}
+
+/// The settings of the command line, which the settings of a document take precedence
+/// over
+#[derive(Clone, Debug, Default)]
+pub struct Defaults {
+    /// Signer source of the fee payer, the default signer pays without one
+    pub fee_payer: Option<String>,
+    pub nonce: Option<NonceSpec>,
+    pub compute_budget: ComputeBudget,
+}
+
+/// A signed transaction and the message it signs, which is signed again when the
+/// transaction is sent with a new blockhash
+pub struct Built {
+    pub transaction: Transaction,
+    pub message: Message,
+    /// The compute unit limit found for `auto`, and the units consumed in the simulation it
+    /// was found with
+    pub auto_unit_limit: Option<(u32, u64)>,
+}
+
+/// The instructions of a document with its nonce section applied
+struct Prepared {
+    payer: Pubkey,
+    instructions: Vec<Instruction>,
+    nonce: Option<(Pubkey, Pubkey)>,
+}
+
+/// Resolves every signer source of `document`, those of its accounts, its fee payer and
+/// its nonce authority, falling back to the sources of `defaults`
+pub fn resolve_signers(
+    document: &Document,
+    signers: &mut Signers,
+    defaults: &Defaults,
+) -> Result<()> {
+    resolve_account_signers(document, signers)?;
+    prepare(
+        document,
+        &mut |source| signers.resolve_or_default(source),
+        defaults,
+    )?;
+    Ok(())
+}
+
+fn resolve_account_signers(document: &Document, signers: &mut Signers) -> Result<()> {
+    for (pubkey, source) in &document.signer_sources {
+        if signers.contains(pubkey) {
+            continue;
+        }
+        let signer_pubkey = signers.resolve(source)?;
+        if signer_pubkey != *pubkey {
+            bail!(
+                "signer {} resolves to {}, but is listed for account {}",
+                source,
+                signer_pubkey,
+                pubkey
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Builds and signs the transaction of `document`. An `auto` compute unit limit is found
+/// by simulating the transaction with `sender`, which also gives the blockhash.
+pub fn build_transaction(
+    document: &Document,
+    signers: &mut Signers,
+    defaults: &Defaults,
+    sender: &mut dyn Sender,
+    send_options: &SendOptions,
+    sign_only: bool,
+) -> Result<Built> {
+    resolve_account_signers(document, signers)?;
+    let prepared = prepare(
+        document,
+        &mut |source| signers.resolve_or_default(source),
+        defaults,
+    )?;
+    // without --blockhash even an offline build needs the cluster's blockhash
+    let blockhash = sender.get_blockhash(
+        prepared.nonce.as_ref().map(|(account, _)| account),
+        send_options,
+    )?;
+    let budget = document.compute_budget.or(&defaults.compute_budget);
+    let mut auto_unit_limit = None;
+    let unit_limit = match budget.unit_limit {
+        Some(ComputeUnitLimit::Units(units)) => Some(units),
+        Some(ComputeUnitLimit::Auto) => {
+            // simulate with the most units allowed, and the price that will be paid
+            let probe = apply_compute_budget(
+                &prepared.instructions,
+                Some(compute_budget::MAX_COMPUTE_UNIT_LIMIT),
+                budget.unit_price,
+            );
+            let message = build_message(&probe, &prepared.payer, prepared.nonce);
+            let consumed = sender::simulate_units(message, blockhash, sender, send_options)
+                .context("failed to find the compute unit limit")?;
+            let units = compute_budget::limit_with_margin(consumed);
+            auto_unit_limit = Some((units, consumed));
+            Some(units)
+        }
+        None => None,
+    };
+    let instructions = apply_compute_budget(&prepared.instructions, unit_limit, budget.unit_price);
+    let message = build_message(&instructions, &prepared.payer, prepared.nonce);
+    let transaction = sign_transaction(message.clone(), signers.signers(), blockhash, sign_only)?;
+    Ok(Built {
+        transaction,
+        message,
+        auto_unit_limit,
+    })
+}
+
+/// Compiles the message of `document` without a cluster or any signer, only the pubkeys
+/// that `resolve` gives for the fee payer and nonce authority. An `auto` compute unit
+/// limit takes as much room as any other, so it is compiled as the largest limit.
+pub fn compile_message(
+    document: &Document,
+    resolve: &mut ResolvePubkey,
+    defaults: &Defaults,
+) -> Result<Message> {
+    let prepared = prepare(document, resolve, defaults)?;
+    let budget = document.compute_budget.or(&defaults.compute_budget);
+    let unit_limit = budget.unit_limit.map(|limit| match limit {
+        ComputeUnitLimit::Units(units) => units,
+        ComputeUnitLimit::Auto => compute_budget::MAX_COMPUTE_UNIT_LIMIT,
+    });
+    let instructions = apply_compute_budget(&prepared.instructions, unit_limit, budget.unit_price);
+    Ok(build_message(
+        &instructions,
+        &prepared.payer,
+        prepared.nonce,
+    ))
+}
+
+/// Resolves the fee payer and nonce authority of `document` and applies its nonce section,
+/// both of which take precedence over `defaults`
+fn prepare(
+    document: &Document,
+    resolve: &mut ResolvePubkey,
+    defaults: &Defaults,
+) -> Result<Prepared> {
+    let payer = resolve(
+        document
+            .fee_payer
+            .as_deref()
+            .or(defaults.fee_payer.as_deref()),
+    )?;
+    let (instructions, nonce) = match document.nonce.as_ref().or(defaults.nonce.as_ref()) {
+        Some(spec) => {
+            let authority = resolve(spec.authority.as_deref())?;
+            apply_nonce(&document.instructions, spec, &payer, &authority)
+        }
+        None => (document.instructions.clone(), None),
+    };
+    Ok(Prepared {
+        payer,
+        instructions,
+        nonce,
+    })
+}
+
+/// Adds the instructions of a `nonce` section to `instructions`.
+///
+/// Returns the `(account, authority)` of the nonce the message has to advance, which is
+/// `None` when the section creates its nonce account instead of using it.
+pub fn apply_nonce(
+    instructions: &[Instruction],
+    spec: &NonceSpec,
+    payer: &Pubkey,
+    authority: &Pubkey,
+) -> (Vec<Instruction>, Option<(Pubkey, Pubkey)>) {
+    let (mut instructions, nonce) = match spec.create {
+        Some(lamports) => {
+            let mut create =
+                system_instruction::create_nonce_account(payer, &spec.account, authority, lamports);
+            create.extend(instructions.iter().cloned());
+            (create, None)
+        }
+        None => (instructions.to_vec(), Some((spec.account, *authority))),
+    };
+    if let Some((to, lamports)) = spec.withdraw {
+        instructions.push(system_instruction::withdraw_nonce_account(
+            &spec.account,
+            authority,
+            &to,
+            lamports,
+        ));
+    }
+    (instructions, nonce)
+}
+
+/// Puts the ComputeBudget instructions that set the compute unit `limit` and `price` in
+/// front of `instructions`
+pub fn apply_compute_budget(
+    instructions: &[Instruction],
+    limit: Option<u32>,
+    price: Option<u64>,
+) -> Vec<Instruction> {
+    let limit = limit.map(compute_budget::set_compute_unit_limit);
+    let price = price.map(compute_budget::set_compute_unit_price);
+    limit
+        .into_iter()
+        .chain(price)
+        .chain(instructions.iter().cloned())
+        .collect()
+}
+
+/// Compiles `instructions` into a message paid for by `payer`. A nonced message advances
+/// its `(account, authority)` nonce in its first instruction.
+pub fn build_message(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    nonce: Option<(Pubkey, Pubkey)>,
+) -> Message {
+    match nonce {
+        Some((account, authority)) => {
+            Message::new_with_nonce(instructions.to_vec(), Some(payer), &account, &authority)
+        }
+        None => Message::new(instructions, Some(payer)),
+    }
+}
+
+/// Signs with every signer whose key the message needs. With `sign_only`, keys without
+/// a signer are left unsigned so that their holders can sign offline.
+pub fn sign_transaction(
+    message: Message,
+    signers: &[Box<dyn Signer>],
+    blockhash: Hash,
+    sign_only: bool,
+) -> Result<Transaction> {
+    let mut transaction = Transaction::new_unsigned(message);
+
+    let signer_keys = transaction.message.signer_keys();
+    let missing_signers = signer_keys
+        .iter()
+        .filter(|key| !signers.iter().any(|signer| &signer.pubkey() == **key))
+        .map(|key| NullSigner::new(key))
+        .collect::<Vec<_>>();
+    if !sign_only && !missing_signers.is_empty() {
+        bail!(
+            "no signer for {}, add a `signer` to the account or pass --signer",
+            missing_signers
+                .iter()
+                .map(|signer| signer.pubkey().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let transaction_signers = signers
+        .iter()
+        .filter(|signer| signer_keys.contains(&&signer.pubkey()))
+        .map(|signer| signer.as_ref())
+        .chain(missing_signers.iter().map(|signer| signer as &dyn Signer))
+        .collect::<Vec<&dyn Signer>>();
+
+    if sign_only {
+        transaction.try_partial_sign(&transaction_signers, blockhash)?;
+    } else {
+        transaction.try_sign(&transaction_signers, blockhash)?;
+    }
+    Ok(transaction)
+}
+
+/// Adds the signatures of `signers` that the transaction needs, keeping the signatures it
+/// already has
+pub fn add_signatures(transaction: &mut Transaction, signers: &[Box<dyn Signer>]) -> Result<()> {
+    let message_data = transaction.message_data();
+    let signer_count = transaction.message.header.num_required_signatures as usize;
+    let signer_keys = transaction.message.account_keys.iter().take(signer_count);
+    for (signature, key) in transaction.signatures.iter_mut().zip(signer_keys) {
+        if let Some(signer) = signers.iter().find(|signer| signer.pubkey() == *key) {
+            let new_signature = signer.try_sign_message(&message_data)?;
+            // a NullSigner must not erase a signature added elsewhere
+            if new_signature != Signature::default() {
+                *signature = new_signature;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The signers whose signatures are still missing
+pub fn absent_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    transaction
+        .message
+        .account_keys
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| *pubkey)
+        .collect()
+}
@@ -0,0 +1,428 @@
+//! Offline checks of the transactions a file describes, run by `soltx check`
+
+use {
+    crate::{
+        builder::{self, Defaults},
+        error::ParseError,
+        fixtures::Fixtures,
+        format::{self, Format},
+        idl::Idls,
+        parse,
+        vars::Variables,
+        Document,
+    },
+    anyhow::Result,
+    solana_sdk::{
+        instruction::Instruction,
+        message::Message,
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Signature, Signer},
+        transaction::Transaction,
+    },
+    std::{fmt, mem::size_of, path::Path},
+};
+
+/// A reason a document would not make a valid transaction
+#[derive(Debug)]
+pub enum Problem {
+    Parse(ParseError),
+    /// An instruction lists the account more than once with different flags. `instruction`
+    /// is 0-based.
+    ConflictingFlags {
+        instruction: usize,
+        account: Pubkey,
+    },
+    /// A signer of the message that has no signer source
+    MissingSigner(Pubkey),
+    /// The pubkey of a signer source cannot be told without loading its signer
+    UnresolvedSigner {
+        source: String,
+        reason: String,
+    },
+    /// The serialized transaction is `size` bytes, more than fit in a packet
+    TooLarge(usize),
+    /// The message references `count` accounts, but only `max` fit in a packet
+    TooManyAccounts {
+        count: usize,
+        max: usize,
+    },
+    /// The fee payer is a sysvar or builtin program, which the runtime never writes
+    ReadonlyFeePayer(Pubkey),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Parse(err) => write!(f, "{}", err),
+            Problem::ConflictingFlags {
+                instruction,
+                account,
+            } => write!(
+                f,
+                "instruction {} lists {} more than once with different isSigner or isWritable \
+                 flags",
+                instruction, account
+            ),
+            Problem::MissingSigner(pubkey) => write!(
+                f,
+                "{} has to sign, but has no signer, add a `signer` to the account or pass --signer",
+                pubkey
+            ),
+            Problem::UnresolvedSigner { source, reason } => {
+                write!(f, "cannot tell the pubkey of signer {}: {}", source, reason)
+            }
+            Problem::TooLarge(size) => write!(
+                f,
+                "the transaction is {} bytes, at most {} fit in a packet",
+                size, PACKET_DATA_SIZE
+            ),
+            Problem::TooManyAccounts { count, max } => write!(
+                f,
+                "the transaction references {} accounts, at most {} fit in a packet",
+                count, max
+            ),
+            Problem::ReadonlyFeePayer(pubkey) => write!(
+                f,
+                "the fee payer {} is not writable, it is a sysvar or builtin program",
+                pubkey
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Problem {}
+
+/// Where a problem was found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    /// The `--signer` sources of the command line
+    SignerSources,
+    /// The document at the 0-based `index` of the `count` in the file
+    Document { index: usize, count: usize },
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::SignerSources => write!(f, "--signer"),
+            Origin::Document { index, count } => write!(f, "document {} of {}", index + 1, count),
+        }
+    }
+}
+
+/// Checks the documents of a file offline. Signer sources are only resolved to their
+/// pubkeys, with [`source_pubkey`], so no signer is ever loaded.
+pub struct Checker<'a> {
+    /// Source of the default signer
+    pub default_signer: &'a str,
+    /// The sources passed with `--signer`
+    pub signer_sources: Vec<String>,
+    pub defaults: &'a Defaults,
+}
+
+impl Checker<'_> {
+    /// Checks every document of `source`, continuing after a document that fails to
+    /// parse, and calls `report` with each problem. Anchor IDLs are only read from files
+    /// relative to `base_dir`. Returns the number of problems.
+    pub fn check_file(
+        &self,
+        source: &str,
+        format: Format,
+        base_dir: &Path,
+        vars: &mut Variables,
+        report: &mut dyn FnMut(Origin, &Problem),
+    ) -> Result<usize> {
+        let nodes = format::load_documents(source, format)?;
+        let mut idls = Idls::offline(base_dir);
+        // fixtures only matter to a test
+        let mut fixtures = Fixtures::default();
+        let mut signers = vec![];
+        let mut problem_count = 0;
+        for source in &self.signer_sources {
+            match source_pubkey(source) {
+                Ok(pubkey) => signers.push(pubkey),
+                Err(problem) => {
+                    report(Origin::SignerSources, &problem);
+                    problem_count += 1;
+                }
+            }
+        }
+        for (index, node) in nodes.iter().enumerate() {
+            let problems = match parse::parse_document(node, vars, &mut idls, &mut fixtures) {
+                Ok(Some(document)) => self.check_document(&document, &signers)?,
+                Ok(None) => vec![],
+                Err(err) => vec![Problem::Parse(err)],
+            };
+            let origin = Origin::Document {
+                index,
+                count: nodes.len(),
+            };
+            for problem in &problems {
+                report(origin, problem);
+            }
+            problem_count += problems.len();
+        }
+        Ok(problem_count)
+    }
+
+    /// Checks a parsed `document`. `signers` are the pubkeys of the `--signer` sources.
+    pub fn check_document(&self, document: &Document, signers: &[Pubkey]) -> Result<Vec<Problem>> {
+        // the fee payer and nonce authority resolve to pubkeys, which are as good as their
+        // signers to a check
+        let mut resolved = vec![];
+        let mut resolve = |source: Option<&str>| -> Result<Pubkey> {
+            let pubkey = source_pubkey(source.unwrap_or(self.default_signer))?;
+            resolved.push(pubkey);
+            Ok(pubkey)
+        };
+        let message = match builder::compile_message(document, &mut resolve, self.defaults) {
+            Ok(message) => message,
+            Err(err) => return err.downcast::<Problem>().map(|problem| vec![problem]),
+        };
+        let available = signers
+            .iter()
+            .copied()
+            .chain(resolved)
+            .chain(document.signer_sources.iter().map(|(pubkey, _)| *pubkey))
+            .collect::<Vec<_>>();
+        Ok(check_message(&document.instructions, &message, &available))
+    }
+}
+
+/// The pubkey of a signer source, found without loading its signer: a pubkey stands for
+/// itself and a keypair file is read for its pubkey. Other sources, like a hardware
+/// wallet or a prompt, only tell their pubkey once they are loaded, which a check never
+/// does.
+pub fn source_pubkey(source: &str) -> Result<Pubkey, Problem> {
+    if let Ok(pubkey) = source.parse() {
+        return Ok(pubkey);
+    }
+    let unresolved = |reason: String| Problem::UnresolvedSigner {
+        source: source.to_string(),
+        reason,
+    };
+    let path = source
+        .strip_prefix("file://")
+        .or_else(|| source.strip_prefix("file:"))
+        .unwrap_or(source);
+    if path == "-" || path == "ASK" || path.contains(':') {
+        return Err(unresolved(
+            "only a pubkey or a keypair file is resolved without loading the signer".to_string(),
+        ));
+    }
+    read_keypair_file(path)
+        .map(|keypair| keypair.pubkey())
+        .map_err(|err| unresolved(format!("failed to read the keypair file: {}", err)))
+}
+
+/// Checks a compiled `message` and the document `instructions` it was compiled from.
+/// `signers` are the pubkeys that have a signer, from the command line or from the
+/// document's signer sources.
+pub fn check_message(
+    instructions: &[Instruction],
+    message: &Message,
+    signers: &[Pubkey],
+) -> Vec<Problem> {
+    let mut problems = vec![];
+    for (index, instruction) in instructions.iter().enumerate() {
+        let mut conflicting: Vec<Pubkey> = vec![];
+        for (position, meta) in instruction.accounts.iter().enumerate() {
+            let conflicts = instruction.accounts[..position].iter().any(|other| {
+                other.pubkey == meta.pubkey
+                    && (other.is_signer, other.is_writable) != (meta.is_signer, meta.is_writable)
+            });
+            if conflicts && !conflicting.contains(&meta.pubkey) {
+                conflicting.push(meta.pubkey);
+            }
+        }
+        problems.extend(
+            conflicting
+                .into_iter()
+                .map(|account| Problem::ConflictingFlags {
+                    instruction: index,
+                    account,
+                }),
+        );
+    }
+
+    problems.extend(
+        message
+            .signer_keys()
+            .into_iter()
+            .filter(|key| !signers.contains(*key))
+            .map(|key| Problem::MissingSigner(*key)),
+    );
+
+    let signature_count = message.header.num_required_signatures as usize;
+    let max = max_accounts(signature_count);
+    if message.account_keys.len() > max {
+        problems.push(Problem::TooManyAccounts {
+            count: message.account_keys.len(),
+            max,
+        });
+    }
+    let transaction = Transaction::new_unsigned(message.clone());
+    // unwrap OK because serializing into memory cannot fail
+    let size = bincode::serialized_size(&transaction).unwrap() as usize;
+    if size > PACKET_DATA_SIZE {
+        problems.push(Problem::TooLarge(size));
+    }
+
+    if !message.is_writable(0, true) {
+        problems.push(Problem::ReadonlyFeePayer(message.account_keys[0]));
+    }
+    problems
+}
+
+/// The most account keys that fit in a packet next to `signature_count` signatures,
+/// leaving no room for any instruction
+fn max_accounts(signature_count: usize) -> usize {
+    // the signature count, the message header, the account count as a 2 byte compact
+    // length, the blockhash and the instruction count
+    let fixed = 1 + signature_count * size_of::<Signature>() + 3 + 2 + 32 + 1;
+    (PACKET_DATA_SIZE.saturating_sub(fixed) / size_of::<Pubkey>()).min(u8::MAX as usize + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            instruction::AccountMeta,
+            signature::{write_keypair_file, Keypair},
+            sysvar,
+        },
+    };
+
+    fn instruction(accounts: Vec<AccountMeta>, data: usize) -> Instruction {
+        Instruction::new_with_bytes(Pubkey::new_unique(), &vec![0; data], accounts)
+    }
+
+    #[test]
+    fn source_pubkeys() {
+        let pubkey = Pubkey::new_unique();
+        assert_eq!(source_pubkey(&pubkey.to_string()).unwrap(), pubkey);
+
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("soltx-check-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(source_pubkey(path).unwrap(), keypair.pubkey());
+        assert_eq!(
+            source_pubkey(&format!("file://{}", path)).unwrap(),
+            keypair.pubkey()
+        );
+        std::fs::remove_file(path).unwrap();
+
+        for source in &[path, "usb://ledger", "prompt://", "-"] {
+            assert!(matches!(
+                source_pubkey(source),
+                Err(Problem::UnresolvedSigner { source: unresolved, .. }) if unresolved == *source
+            ));
+        }
+    }
+
+    #[test]
+    fn checks_every_document() {
+        let (from, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let source = format!(
+            "instrctions: []\n\
+             ---\n\
+             - transfer: {{from: {0}, to: {0}, lamports: 1}}\n\
+             ---\n\
+             feePayer: {1}\n\
+             instructions:\n  - transfer: {{from: {0}, to: {1}, lamports: 1}}\n",
+            from, payer
+        );
+        let defaults = Defaults::default();
+        let checker = Checker {
+            default_signer: "usb://ledger",
+            signer_sources: vec!["prompt://".to_string()],
+            defaults: &defaults,
+        };
+        let mut problems = vec![];
+        let count = checker
+            .check_file(
+                &source,
+                Format::Yaml,
+                Path::new(""),
+                &mut Variables::default(),
+                &mut |origin, problem| problems.push((origin, problem.to_string())),
+            )
+            .unwrap();
+        assert_eq!(count, 4);
+        let document = |index| Origin::Document { index, count: 3 };
+        assert_eq!(problems[0].0, Origin::SignerSources);
+        assert!(problems[0].1.contains("prompt://"));
+        assert_eq!(problems[1].0, document(0));
+        assert!(problems[1].1.contains("instrctions"));
+        assert_eq!(problems[2].0, document(1));
+        assert!(problems[2].1.contains("usb://ledger"));
+        assert_eq!(problems[3].0, document(2));
+        assert!(problems[3].1.starts_with(&format!("{} has to sign", from)));
+    }
+
+    #[test]
+    fn a_valid_message_has_no_problems() {
+        let payer = Pubkey::new_unique();
+        let instructions = vec![instruction(
+            vec![AccountMeta::new(Pubkey::new_unique(), false)],
+            8,
+        )];
+        let message = Message::new(&instructions, Some(&payer));
+        assert!(check_message(&instructions, &message, &[payer]).is_empty());
+    }
+
+    #[test]
+    fn conflicting_flags_and_missing_signers() {
+        let (payer, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = vec![instruction(
+            vec![
+                AccountMeta::new(account, true),
+                AccountMeta::new_readonly(account, false),
+                AccountMeta::new_readonly(account, true),
+            ],
+            0,
+        )];
+        let message = Message::new(&instructions, Some(&payer));
+        let problems = check_message(&instructions, &message, &[payer]);
+        assert!(matches!(
+            problems.as_slice(),
+            [
+                Problem::ConflictingFlags { instruction: 0, account: conflicting },
+                Problem::MissingSigner(missing),
+            ] if *conflicting == account && *missing == account
+        ));
+    }
+
+    #[test]
+    fn oversized_transactions() {
+        let payer = Pubkey::new_unique();
+        let instructions = vec![instruction(vec![], PACKET_DATA_SIZE)];
+        let message = Message::new(&instructions, Some(&payer));
+        let problems = check_message(&instructions, &message, &[payer]);
+        assert!(
+            matches!(problems.as_slice(), [Problem::TooLarge(size)] if *size > PACKET_DATA_SIZE)
+        );
+
+        let accounts = (0..40)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect();
+        let instructions = vec![instruction(accounts, 0)];
+        let message = Message::new(&instructions, Some(&payer));
+        let problems = check_message(&instructions, &message, &[payer]);
+        assert!(problems
+            .iter()
+            .any(|problem| matches!(problem, Problem::TooManyAccounts { count: 42, .. })));
+    }
+
+    #[test]
+    fn readonly_fee_payer() {
+        let payer = sysvar::clock::id();
+        let instructions = vec![instruction(vec![], 0)];
+        let message = Message::new(&instructions, Some(&payer));
+        let problems = check_message(&instructions, &message, &[payer]);
+        assert!(matches!(problems.as_slice(), [Problem::ReadonlyFeePayer(key)] if *key == payer));
+    }
+}
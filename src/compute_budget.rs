@@ -0,0 +1,62 @@
+//! Instructions of the ComputeBudget program, which solana-sdk 1.6 has no builders for
+
+use solana_sdk::instruction::Instruction;
+
+solana_sdk::declare_id!("ComputeBudget111111111111111111111111111111");
+
+/// The most compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// The margin in percent that an automatic limit adds to the simulated units
+pub const AUTO_MARGIN_PERCENT: u64 = 10;
+
+/// The units an automatic limit allows for an instruction of a builtin program, like the
+/// system program. Builtins log no consumed units, and none uses more than this.
+pub const BUILTIN_INSTRUCTION_UNITS: u64 = 3_000;
+
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![2];
+    data.extend(&units.to_le_bytes());
+    Instruction::new_with_bytes(id(), &data, vec![])
+}
+
+/// Sets the priority fee, in micro-lamports per compute unit
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![3];
+    data.extend(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(id(), &data, vec![])
+}
+
+/// The limit for a transaction that consumed `units` in a simulation
+pub fn limit_with_margin(units: u64) -> u32 {
+    let units = units + units * AUTO_MARGIN_PERCENT / 100;
+    units.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    #[test]
+    fn instructions() {
+        let program_id =
+            solana_sdk::pubkey::Pubkey::from_str("ComputeBudget111111111111111111111111111111")
+                .unwrap();
+        let limit = set_compute_unit_limit(200_000);
+        assert_eq!(limit.program_id, program_id);
+        assert!(limit.accounts.is_empty());
+        assert_eq!(limit.data, vec![2, 0x40, 0x0d, 0x03, 0x00]);
+        let price = set_compute_unit_price(1);
+        assert_eq!(price.program_id, program_id);
+        assert!(price.accounts.is_empty());
+        assert_eq!(price.data, vec![3, 1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn limits() {
+        assert_eq!(limit_with_margin(1_000), 1_100);
+        assert_eq!(limit_with_margin(BUILTIN_INSTRUCTION_UNITS), 3_300);
+        assert_eq!(limit_with_margin(1_300_000), MAX_COMPUTE_UNIT_LIMIT);
+        assert_eq!(limit_with_margin(u32::MAX as u64), MAX_COMPUTE_UNIT_LIMIT);
+    }
+}
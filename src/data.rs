@@ -0,0 +1,127 @@
+use {
+    crate::{
+        error::{ParseError, ParseErrorKind},
+        spec::{DataSpec, FieldSpec, Number},
+        yaml::{from_node, Node},
+    },
+    std::str::FromStr,
+};
+
+/// Parses the `data` of an instruction.
+///
+/// `data` is either comma separated decimal bytes (`0,1,255`), a single encoded value
+/// (`hex: 0001ff`) or a sequence of typed fields (`[{u8: 1}, {u64: 1000000}]`) which
+/// are concatenated, see [`DataSpec`].
+pub fn parse_data(yaml: &Node, path: &str) -> Result<Vec<u8>, ParseError> {
+    from_node::<DataSpec>(yaml, path).map(|data| data.to_bytes())
+}
+
+/// Parses a single-entry mapping such as `{u64: 5}` into its bytes, see [`FieldSpec`]
+pub fn parse_field(yaml: &Node, path: &str) -> Result<Vec<u8>, ParseError> {
+    from_node::<FieldSpec>(yaml, path).map(|field| field.to_bytes())
+}
+
+/// Splits a single-entry mapping like `{u64: 5}` into its tag and value
+pub fn tagged<'a>(
+    yaml: &'a Node,
+    path: &str,
+    expected: &'static str,
+) -> Result<(&'a str, &'a Node), ParseError> {
+    match yaml.as_mapping() {
+        Some([(tag, value)]) => match tag.as_str() {
+            Some(tag) => Ok((tag, value)),
+            None => Err(ParseError::invalid_type(tag, path, "a string")),
+        },
+        Some(_) => Err(ParseError::new(
+            yaml,
+            path,
+            ParseErrorKind::ExpectedTagged(expected.to_string()),
+        )),
+        None => Err(ParseError::invalid_type(yaml, path, "a mapping")),
+    }
+}
+
+/// Numbers may be written as yaml integers or, for values that do not fit an `i64`,
+/// as strings
+pub fn number<T: FromStr>(yaml: &Node, path: &str) -> Result<T, ParseError> {
+    from_node::<Number<T>>(yaml, path).map(|Number(number)| number)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::yaml::load_from_str, solana_sdk::pubkey::Pubkey};
+
+    fn node(source: &str) -> Node {
+        load_from_str(source).unwrap().remove(0)
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(
+            parse_data(&node("0, 1,255"), "data").unwrap(),
+            vec![0, 1, 255]
+        );
+        assert_eq!(parse_data(&node("7"), "data").unwrap(), vec![7]);
+        assert_eq!(parse_data(&node("''"), "data").unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(
+            parse_data(&node("hex: 00ff"), "data").unwrap(),
+            vec![0, 255]
+        );
+        assert_eq!(parse_data(&node("base58: '2'"), "data").unwrap(), vec![1]);
+        assert_eq!(
+            parse_data(&node("base64: AP8="), "data").unwrap(),
+            vec![0, 255]
+        );
+        assert_eq!(
+            parse_data(&node("utf8: hi"), "data").unwrap(),
+            b"hi".to_vec()
+        );
+    }
+
+    #[test]
+    fn typed_fields_are_borsh_encoded() {
+        let pubkey = Pubkey::new_unique();
+        let data = parse_data(
+            &node(&format!(
+                "[{{u8: 1}}, {{u16: 2}}, {{i32: -1}}, {{u64: '18446744073709551615'}}, \
+                 {{bool: true}}, {{string: hi}}, {{pubkey: {}}}]",
+                pubkey
+            )),
+            "data",
+        )
+        .unwrap();
+        let mut expected = vec![1, 2, 0, 255, 255, 255, 255];
+        expected.extend(&u64::MAX.to_le_bytes());
+        expected.extend(&[1, 2, 0, 0, 0, b'h', b'i']);
+        expected.extend(&pubkey.to_bytes());
+        assert_eq!(data, expected);
+        assert_eq!(
+            parse_field(&node("i128: -2"), "seed").unwrap(),
+            (-2i128).to_le_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_value() {
+        let source = "data: {u8: 300}";
+        let err = parse_data(node(source).get("data").unwrap(), "data").unwrap_err();
+        assert_eq!(err.path, "data.u8");
+        assert_eq!((err.line, err.column), (1, 12));
+        assert!(matches!(err.kind, ParseErrorKind::InvalidValue { .. }));
+
+        let err = parse_data(&node("1,2,256"), "data").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid byte `256`, expected a number from 0 to 255"));
+        let err = parse_data(&node("u7: 1"), "data").unwrap_err();
+        assert!(matches!(err.kind, ParseErrorKind::UnknownTag { tag, .. } if tag == "u7"));
+        let err = parse_data(&node("{u8: 1, u16: 2}"), "data").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("expected a mapping with a single key"));
+    }
+}
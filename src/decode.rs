@@ -0,0 +1,85 @@
+use {
+    crate::{
+        format::Format,
+        spec::{TransactionSpec, TxFileSpec},
+    },
+    anyhow::Result,
+    solana_sdk::transaction::Transaction,
+    std::fmt::Write,
+};
+
+/// Writes `transactions` as a transaction file, in the shape that `parse_document` reads
+/// back. yaml gets one document per transaction, headed by comments with its signature,
+/// fee payer and blockhash. json and toml get a `documents` list.
+pub fn transactions_to_string(transactions: &[Transaction], format: Format) -> Result<String> {
+    let documents = transactions.iter().map(TransactionSpec::from);
+    match format {
+        Format::Yaml => {
+            let mut yaml = String::new();
+            for (transaction, document) in transactions.iter().zip(documents) {
+                let message = &transaction.message;
+                writeln!(yaml, "# signature: {}", transaction.signatures[0])?;
+                writeln!(yaml, "# fee payer: {}", message.account_keys[0])?;
+                writeln!(yaml, "# blockhash: {}", message.recent_blockhash)?;
+                writeln!(yaml, "{}", serde_yaml::to_string(&document)?)?;
+            }
+            Ok(yaml)
+        }
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&TxFileSpec {
+                documents: documents.collect(),
+            })?;
+            Ok(json + "\n")
+        }
+        Format::Toml => Ok(toml::to_string(&TxFileSpec {
+            documents: documents.collect(),
+        })?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{idl::Idls, vars::Variables, TxFile},
+        solana_sdk::{
+            instruction::{AccountMeta, Instruction},
+            message::Message,
+            pubkey::Pubkey,
+        },
+    };
+
+    #[test]
+    fn round_trip() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let instructions = vec![
+            Instruction::new_with_bytes(
+                program_id,
+                &[1, 2, 3],
+                vec![
+                    AccountMeta::new(Pubkey::new_unique(), true),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                ],
+            ),
+            Instruction::new_with_bytes(program_id, &[], vec![]),
+        ];
+        let message = Message::new(&instructions, Some(&payer));
+        let transaction = Transaction::new_unsigned(message.clone());
+        for format in &[Format::Yaml, Format::Json, Format::Toml] {
+            let source = transactions_to_string(&[transaction.clone()], *format).unwrap();
+            let tx_file = TxFile::parse(
+                &source,
+                *format,
+                &mut Variables::default(),
+                &mut Idls::offline(""),
+            )
+            .unwrap();
+            assert_eq!(tx_file.documents.len(), 1);
+            let document = &tx_file.documents[0];
+            assert_eq!(document.instructions, instructions);
+            assert_eq!(document.fee_payer, None);
+            assert_eq!(Message::new(&document.instructions, Some(&payer)), message);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use {crate::yaml::Node, std::fmt, yaml_rust::ScanError};
+
+/// An error in the contents of a transaction file, located by its yaml path and
+/// source position.
+#[derive(Debug)]
+pub struct ParseError {
+    /// Path to the offending node, e.g. `[0].accounts[3].key`
+    pub path: String,
+    /// 1-based line of the offending node, 0 in json and toml files
+    pub line: usize,
+    /// 1-based column of the offending node
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    MissingField(&'static str),
+    /// The node has the wrong type, holds a description of the expected type
+    InvalidType {
+        expected: String,
+        found: &'static str,
+    },
+    InvalidPubkey(String),
+    InvalidByte(String),
+    /// A tagged value such as `{u64: 5}` used an unknown tag
+    UnknownTag {
+        tag: String,
+        expected: String,
+    },
+    /// A mapping that should hold exactly one of the listed tags
+    ExpectedTagged(String),
+    InvalidValue {
+        value: String,
+        expected: String,
+    },
+    InvalidEncoding(String),
+    UndefinedVariable(String),
+    InvalidSeeds(String),
+    /// A mapping key that is not one of the fields the mapping takes
+    UnknownField {
+        field: String,
+        expected: String,
+    },
+    /// A shorthand instruction whose `program.` prefix names no program with shorthands
+    UnknownProgram {
+        program: String,
+        expected: &'static str,
+    },
+    /// An Anchor instruction that does not match its IDL
+    Idl(String),
+    /// A message of a `Deserialize` implementation of the serde model
+    Custom(String),
+}
+
+impl ParseError {
+    pub fn new(node: &Node, path: &str, kind: ParseErrorKind) -> Self {
+        Self {
+            path: path.to_string(),
+            line: node.position.line,
+            column: node.position.column,
+            kind,
+        }
+    }
+
+    pub fn invalid_type(node: &Node, path: &str, expected: &'static str) -> Self {
+        Self::new(
+            node,
+            path,
+            ParseErrorKind::InvalidType {
+                expected: expected.to_string(),
+                found: node.type_name(),
+            },
+        )
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::MissingField(field) => write!(f, "missing field `{}`", field),
+            ParseErrorKind::InvalidType { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::InvalidPubkey(key) => write!(f, "invalid pubkey `{}`", key),
+            ParseErrorKind::InvalidByte(byte) => {
+                write!(
+                    f,
+                    "invalid byte `{}`, expected a number from 0 to 255",
+                    byte
+                )
+            }
+            ParseErrorKind::UnknownTag { tag, expected } => {
+                write!(f, "unknown tag `{}`, expected one of {}", tag, expected)
+            }
+            ParseErrorKind::ExpectedTagged(expected) => {
+                write!(
+                    f,
+                    "expected a mapping with a single key, one of {}",
+                    expected
+                )
+            }
+            ParseErrorKind::InvalidValue { value, expected } => {
+                write!(f, "invalid value `{}`, expected {}", value, expected)
+            }
+            ParseErrorKind::InvalidEncoding(err) => write!(f, "invalid encoding: {}", err),
+            ParseErrorKind::UndefinedVariable(name) => write!(f, "undefined variable `${}`", name),
+            ParseErrorKind::InvalidSeeds(err) => write!(f, "invalid seeds: {}", err),
+            ParseErrorKind::UnknownField { field, expected } => {
+                write!(f, "unknown field `{}`, expected one of {}", field, expected)
+            }
+            ParseErrorKind::UnknownProgram { program, expected } => write!(
+                f,
+                "unknown program `{}` for a shorthand, expected {} or no prefix for the system \
+                 and memo programs",
+                program, expected
+            ),
+            ParseErrorKind::Idl(err) => write!(f, "{}", err),
+            ParseErrorKind::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "document root"
+        } else {
+            &self.path
+        };
+        write!(f, "{} at {}", self.kind, path)?;
+        // json and toml nodes carry no position
+        if self.line > 0 {
+            write!(f, " (line {}, column {})", self.line, self.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// An error loading a whole transaction file
+#[derive(Debug)]
+pub enum FileError {
+    Io(std::io::Error),
+    Yaml(ScanError),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// Document `index` (0-based) of the `count` documents in the file is invalid
+    Document {
+        index: usize,
+        count: usize,
+        error: ParseError,
+    },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(_) => write!(f, "failed to read the file"),
+            FileError::Yaml(_) => write!(f, "invalid yaml"),
+            FileError::Json(_) => write!(f, "invalid json"),
+            FileError::Toml(_) => write!(f, "invalid toml"),
+            FileError::Document { index, count, .. } => {
+                write!(f, "document {} of {} failed", index + 1, count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io(err) => Some(err),
+            FileError::Yaml(err) => Some(err),
+            FileError::Json(err) => Some(err),
+            FileError::Toml(err) => Some(err),
+            FileError::Document { error, .. } => Some(error),
+        }
+    }
+}
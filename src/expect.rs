@@ -0,0 +1,462 @@
+//! The `expect` section of a document, which is checked once its transaction executed
+
+use {
+    crate::{
+        data::{number, parse_field},
+        error::{ParseError, ParseErrorKind},
+        parse::{check_fields, field, parse_pubkey, SendOptions},
+        sender::{Attempt, Sender},
+        yaml::{Node, Value},
+        Document,
+    },
+    anyhow::{bail, Result},
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::{Transaction, TransactionError},
+    },
+    std::fmt,
+};
+
+const EXPECTATION_KINDS: &str = "account, error or logsContain";
+
+/// Something the transaction of a document is expected to have done
+#[derive(Debug)]
+pub enum Expectation {
+    Account(AccountExpectation),
+    /// The transaction failed. `instruction` is the 0-based position of the failed
+    /// instruction in the transaction, as in the runtime's error, and `custom` its custom
+    /// program error.
+    Error {
+        instruction: Option<u8>,
+        custom: Option<u32>,
+    },
+    /// One of the transaction's log messages contains this
+    LogsContain(String),
+}
+
+/// The state of an account after the transaction, unset fields are not checked
+#[derive(Debug)]
+pub struct AccountExpectation {
+    pub address: Pubkey,
+    pub lamports: Option<u64>,
+    pub owner: Option<Pubkey>,
+    pub data_len: Option<usize>,
+    /// Bytes expected at an offset of the account data
+    pub data: Vec<(usize, Vec<u8>)>,
+}
+
+/// How a transaction executed
+#[derive(Debug)]
+pub struct Execution {
+    pub err: Option<TransactionError>,
+    /// Empty where the sender does not return logs, which
+    /// [`Sender::returns_logs`](crate::sender::Sender::returns_logs) tells up front
+    pub logs: Vec<String>,
+}
+
+/// An expectation that the transaction did not meet
+#[derive(Debug)]
+pub enum Mismatch {
+    /// A field of an account differs, `expected` and `found` are formatted for the diff
+    Account {
+        address: Pubkey,
+        field: String,
+        expected: String,
+        found: String,
+    },
+    MissingAccount(Pubkey),
+    Error {
+        instruction: Option<u8>,
+        custom: Option<u32>,
+        found: Option<TransactionError>,
+    },
+    /// The transaction failed without an `error` expectation
+    UnexpectedError(TransactionError),
+    MissingLog(String),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Account {
+                address,
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "account {} {}\n  - {}\n  + {}",
+                address, field, expected, found
+            ),
+            Mismatch::MissingAccount(address) => write!(f, "account {} does not exist", address),
+            Mismatch::Error {
+                instruction,
+                custom,
+                found,
+            } => {
+                write!(f, "error\n  - ")?;
+                match (instruction, custom) {
+                    (None, None) => write!(f, "any error")?,
+                    (Some(instruction), None) => {
+                        write!(f, "any error in instruction {}", instruction)?
+                    }
+                    (None, Some(custom)) => write!(f, "custom program error {}", custom)?,
+                    (Some(instruction), Some(custom)) => write!(
+                        f,
+                        "custom program error {} in instruction {}",
+                        custom, instruction
+                    )?,
+                }
+                match found {
+                    Some(err) => write!(f, "\n  + {}", err),
+                    None => write!(f, "\n  + no error"),
+                }
+            }
+            Mismatch::UnexpectedError(err) => write!(f, "the transaction failed: {}", err),
+            Mismatch::MissingLog(log) => write!(f, "no log message contains `{}`", log),
+        }
+    }
+}
+
+/// Parses an `expect` section, a single expectation or a sequence of them
+pub fn parse_expectations(yaml: &Node, path: &str) -> Result<Vec<Expectation>, ParseError> {
+    match yaml.as_sequence() {
+        Some(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| parse_expectation(item, &format!("{}[{}]", path, index)))
+            .collect(),
+        None => Ok(vec![parse_expectation(yaml, path)?]),
+    }
+}
+
+/// `{account, lamports, owner, dataLen, data}`, `{error: {instruction, custom}}` or
+/// `{logsContain}`
+fn parse_expectation(yaml: &Node, path: &str) -> Result<Expectation, ParseError> {
+    if yaml.as_mapping().is_none() {
+        return Err(ParseError::invalid_type(yaml, path, "a mapping"));
+    }
+    if yaml.get("account").is_some() {
+        check_fields(
+            yaml,
+            path,
+            &["account", "lamports", "owner", "dataLen", "data"],
+        )?;
+        let optional = |name: &str| {
+            yaml.get(name)
+                .map(|node| (node, format!("{}.{}", path, name)))
+        };
+        let data = match optional("data") {
+            Some((data, path)) => match data.as_sequence() {
+                Some(items) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| parse_data_at(item, &format!("{}[{}]", path, index)))
+                    .collect::<Result<_, _>>()?,
+                None => vec![parse_data_at(data, &path)?],
+            },
+            None => vec![],
+        };
+        return Ok(Expectation::Account(AccountExpectation {
+            address: parse_pubkey(field(yaml, path, "account")?, &format!("{}.account", path))?,
+            lamports: optional("lamports")
+                .map(|(node, path)| number(node, &path))
+                .transpose()?,
+            owner: optional("owner")
+                .map(|(node, path)| parse_pubkey(node, &path))
+                .transpose()?,
+            data_len: optional("dataLen")
+                .map(|(node, path)| number(node, &path))
+                .transpose()?,
+            data,
+        }));
+    }
+    if let Some(error) = yaml.get("error") {
+        check_fields(yaml, path, &["error"])?;
+        let path = &format!("{}.error", path);
+        // `error: {}` expects any error
+        if error.as_mapping().is_none() {
+            return Err(ParseError::invalid_type(error, path, "a mapping"));
+        }
+        check_fields(error, path, &["instruction", "custom"])?;
+        return Ok(Expectation::Error {
+            instruction: error
+                .get("instruction")
+                .map(|node| number(node, &format!("{}.instruction", path)))
+                .transpose()?,
+            custom: error
+                .get("custom")
+                .map(|node| number(node, &format!("{}.custom", path)))
+                .transpose()?,
+        });
+    }
+    if let Some(log) = yaml.get("logsContain") {
+        check_fields(yaml, path, &["logsContain"])?;
+        let path = &format!("{}.logsContain", path);
+        let log = log
+            .as_str()
+            .ok_or_else(|| ParseError::invalid_type(log, path, "a string"))?;
+        return Ok(Expectation::LogsContain(log.to_string()));
+    }
+    Err(ParseError::new(
+        yaml,
+        path,
+        ParseErrorKind::ExpectedTagged(EXPECTATION_KINDS.to_string()),
+    ))
+}
+
+/// `{offset, <field>: value}`, where the field is written like a typed field of
+/// instruction data, e.g. `{offset: 64, u64: 500}`
+fn parse_data_at(yaml: &Node, path: &str) -> Result<(usize, Vec<u8>), ParseError> {
+    let entries = yaml
+        .as_mapping()
+        .ok_or_else(|| ParseError::invalid_type(yaml, path, "a mapping"))?;
+    let offset = number(field(yaml, path, "offset")?, &format!("{}.offset", path))?;
+    let value = Node {
+        value: Value::Mapping(
+            entries
+                .iter()
+                .filter(|(key, _)| key.as_str() != Some("offset"))
+                .cloned()
+                .collect(),
+        ),
+        position: yaml.position,
+    };
+    Ok((offset, parse_field(&value, path)?))
+}
+
+/// Sends the transaction of `document` and checks its `expect` section. A transaction that
+/// fails as expected succeeds. `report` is called with each attempt to send it.
+pub fn send_expecting(
+    sender: &mut dyn Sender,
+    transaction: &mut Transaction,
+    document: &Document,
+    send_options: &SendOptions,
+    resign: &mut dyn FnMut(Hash) -> Result<Transaction>,
+    report: &mut dyn FnMut(&Attempt),
+) -> Result<Signature> {
+    let result = sender.send(transaction, send_options, resign, report);
+    if document.expectations.is_empty() {
+        return result;
+    }
+    let execution = match sender.execution(transaction, &result, send_options)? {
+        Some(execution) => execution,
+        None => return result,
+    };
+    let mismatches = check(&document.expectations, &execution, |address| {
+        sender.get_account(address, send_options)
+    })?;
+    if !mismatches.is_empty() {
+        let diff = mismatches
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        bail!("the transaction did not meet its expectations:\n{}", diff);
+    }
+    Ok(transaction.signatures[0])
+}
+
+/// Checks `expectations` against how the transaction executed. `get_account` fetches the
+/// state of an account after the transaction.
+pub fn check(
+    expectations: &[Expectation],
+    execution: &Execution,
+    mut get_account: impl FnMut(&Pubkey) -> Result<Option<Account>>,
+) -> Result<Vec<Mismatch>> {
+    let mut mismatches = vec![];
+    let expects_error = expectations
+        .iter()
+        .any(|expectation| matches!(expectation, Expectation::Error { .. }));
+    if let (Some(err), false) = (&execution.err, expects_error) {
+        mismatches.push(Mismatch::UnexpectedError(err.clone()));
+    }
+    for expectation in expectations {
+        match expectation {
+            Expectation::Account(expected) => match get_account(&expected.address)? {
+                Some(account) => mismatches.extend(check_account(expected, &account)),
+                None => mismatches.push(Mismatch::MissingAccount(expected.address)),
+            },
+            Expectation::Error {
+                instruction,
+                custom,
+            } => {
+                let matched = match &execution.err {
+                    None => false,
+                    Some(TransactionError::InstructionError(index, err)) => {
+                        instruction.map_or(true, |instruction| instruction == *index)
+                            && custom
+                                .map_or(true, |custom| *err == InstructionError::Custom(custom))
+                    }
+                    Some(_) => instruction.is_none() && custom.is_none(),
+                };
+                if !matched {
+                    mismatches.push(Mismatch::Error {
+                        instruction: *instruction,
+                        custom: *custom,
+                        found: execution.err.clone(),
+                    });
+                }
+            }
+            Expectation::LogsContain(log) => {
+                if !execution
+                    .logs
+                    .iter()
+                    .any(|line| line.contains(log.as_str()))
+                {
+                    mismatches.push(Mismatch::MissingLog(log.clone()));
+                }
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+fn check_account(expectation: &AccountExpectation, account: &Account) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    let mut compare = |field: String, expected: String, found: String| {
+        if expected != found {
+            mismatches.push(Mismatch::Account {
+                address: expectation.address,
+                field,
+                expected,
+                found,
+            });
+        }
+    };
+    if let Some(lamports) = expectation.lamports {
+        compare(
+            "lamports".to_string(),
+            lamports.to_string(),
+            account.lamports.to_string(),
+        );
+    }
+    if let Some(owner) = expectation.owner {
+        compare(
+            "owner".to_string(),
+            owner.to_string(),
+            account.owner.to_string(),
+        );
+    }
+    if let Some(data_len) = expectation.data_len {
+        compare(
+            "dataLen".to_string(),
+            data_len.to_string(),
+            account.data.len().to_string(),
+        );
+    }
+    for (offset, bytes) in &expectation.data {
+        // data that ends early shows as the bytes there are
+        let start = (*offset).min(account.data.len());
+        let end = offset.saturating_add(bytes.len()).min(account.data.len());
+        compare(
+            format!("data at offset {}", offset),
+            format!("0x{}", hex::encode(bytes)),
+            format!("0x{}", hex::encode(&account.data[start..end])),
+        );
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::yaml::load_from_str};
+
+    fn expectations(source: &str) -> Vec<Expectation> {
+        parse_expectations(&load_from_str(source).unwrap().remove(0), "expect").unwrap()
+    }
+
+    fn execution(err: Option<TransactionError>) -> Execution {
+        Execution {
+            err,
+            logs: vec!["Program log: done".to_string()],
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let err = TransactionError::InstructionError(1, InstructionError::Custom(6000));
+        let no_account = |_: &Pubkey| Ok(None);
+        for (source, mismatches) in &[
+            ("error: {}", 0),
+            ("error: {instruction: 1, custom: 6000}", 0),
+            ("error: {instruction: 0}", 1),
+            ("error: {custom: 1}", 1),
+            ("logsContain: done", 1),
+        ] {
+            let found = check(
+                &expectations(source),
+                &execution(Some(err.clone())),
+                no_account,
+            )
+            .unwrap();
+            assert_eq!(found.len(), *mismatches, "{}", source);
+        }
+        let found = check(
+            &expectations("logsContain: done"),
+            &execution(Some(err)),
+            no_account,
+        )
+        .unwrap();
+        assert!(matches!(found.as_slice(), [Mismatch::UnexpectedError(_)]));
+        let found = check(&expectations("error: {}"), &execution(None), no_account).unwrap();
+        assert!(matches!(
+            found.as_slice(),
+            [Mismatch::Error { found: None, .. }]
+        ));
+    }
+
+    #[test]
+    fn logs() {
+        let found = check(
+            &expectations("[{logsContain: done}, {logsContain: missing}]"),
+            &execution(None),
+            |_| Ok(None),
+        )
+        .unwrap();
+        assert!(matches!(found.as_slice(), [Mismatch::MissingLog(log)] if log == "missing"));
+    }
+
+    #[test]
+    fn accounts() {
+        let (address, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = Account {
+            lamports: 10,
+            data: vec![1, 2, 3, 4],
+            owner,
+            ..Account::default()
+        };
+        let expected = expectations(&format!(
+            "account: {}\nlamports: 10\nowner: {}\ndataLen: 4\ndata: {{offset: 1, hex: '0203'}}",
+            address, owner
+        ));
+        let found = check(&expected, &execution(None), |_| Ok(Some(account.clone()))).unwrap();
+        assert!(found.is_empty());
+
+        let expected = expectations(&format!(
+            "account: {}\nlamports: 11\ndata: [{{offset: 3, u16: 4}}]",
+            address
+        ));
+        let found = check(&expected, &execution(None), |_| Ok(Some(account.clone()))).unwrap();
+        let fields = found
+            .iter()
+            .map(|mismatch| match mismatch {
+                Mismatch::Account { field, found, .. } => (field.as_str(), found.as_str()),
+                mismatch => panic!("unexpected mismatch {}", mismatch),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![("lamports", "10"), ("data at offset 3", "0x04")]
+        );
+
+        let found = check(&expected, &execution(None), |_| Ok(None)).unwrap();
+        assert!(
+            matches!(found.as_slice(), [Mismatch::MissingAccount(missing)] if *missing == address)
+        );
+    }
+}
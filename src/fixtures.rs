@@ -0,0 +1,350 @@
+//! The `programs` and `fixtures` sections, which set up the in-process bank of `soltx test`
+
+use {
+    crate::{
+        data::{number, parse_data},
+        error::{ParseError, ParseErrorKind},
+        parse::{check_fields, field, parse_pubkey},
+        vars::Variables,
+        yaml::Node,
+    },
+    anyhow::{anyhow, bail, Context, Result},
+    solana_account_decoder::{UiAccount, UiAccountEncoding},
+    solana_clap_utils::input_validators::normalize_to_url_if_moniker,
+    solana_client::{rpc_client::RpcClient, rpc_response::RpcKeyedAccount},
+    solana_sdk::{
+        account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, system_program,
+    },
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Where cloned accounts are cached by default, relative to the transaction file
+const CACHE_DIR: &str = "fixtures";
+
+/// The programs and accounts of every document in a file
+#[derive(Debug, Default)]
+pub struct Fixtures {
+    pub programs: Vec<ProgramFixture>,
+    pub accounts: Vec<AccountFixture>,
+}
+
+/// A program deployed from a shared object file
+#[derive(Debug)]
+pub struct ProgramFixture {
+    pub address: Pubkey,
+    /// Path to the `.so` file, relative to the transaction file
+    pub file: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum AccountFixture {
+    /// An account written out in the file
+    Inline {
+        address: Pubkey,
+        lamports: u64,
+        /// Defaults to the system program
+        owner: Pubkey,
+        executable: bool,
+        data: AccountData,
+    },
+    /// An account cloned from the cluster at `url`. It is cached in `cache`, relative to
+    /// the transaction file, and only fetched again once that file is deleted.
+    Clone {
+        address: Pubkey,
+        url: String,
+        cache: PathBuf,
+    },
+    /// A file written by `solana account --output json`, relative to the transaction file
+    Dump(PathBuf),
+}
+
+#[derive(Debug)]
+pub enum AccountData {
+    Bytes(Vec<u8>),
+    /// Path to a file with the raw data, relative to the transaction file
+    File(PathBuf),
+}
+
+impl Fixtures {
+    /// Adds the `programs` and `fixtures` sections of the document `yaml`
+    pub fn parse(&mut self, yaml: &Node, vars: &Variables) -> Result<(), ParseError> {
+        if let Some(programs) = yaml.get("programs") {
+            let programs = vars.substitute(programs, "programs")?;
+            for (path, program) in sequence(&programs, "programs")? {
+                self.programs.push(parse_program(program, &path)?);
+            }
+        }
+        if let Some(accounts) = yaml.get("fixtures") {
+            let accounts = vars.substitute(accounts, "fixtures")?;
+            for (path, account) in sequence(&accounts, "fixtures")? {
+                self.accounts.push(parse_account(account, &path)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads, fetches or clones the accounts, whose files are relative to `base_dir`
+    pub fn load_accounts(&self, base_dir: &Path) -> Result<Vec<(Pubkey, Account)>> {
+        self.accounts
+            .iter()
+            .map(|fixture| load_account(fixture, base_dir))
+            .collect()
+    }
+}
+
+fn load_account(fixture: &AccountFixture, base_dir: &Path) -> Result<(Pubkey, Account)> {
+    match fixture {
+        AccountFixture::Inline {
+            address,
+            lamports,
+            owner,
+            executable,
+            data,
+        } => {
+            let data = match data {
+                AccountData::Bytes(bytes) => bytes.clone(),
+                AccountData::File(file) => {
+                    let path = base_dir.join(file);
+                    fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?
+                }
+            };
+            let account = Account {
+                lamports: *lamports,
+                data,
+                owner: *owner,
+                executable: *executable,
+                rent_epoch: 0,
+            };
+            Ok((*address, account))
+        }
+        AccountFixture::Clone {
+            address,
+            url,
+            cache,
+        } => {
+            let path = base_dir.join(cache);
+            if path.exists() {
+                let (cached, account) = read_dump(&path)?;
+                if cached != *address {
+                    bail!("{} caches {}, not {}", path.display(), cached, address);
+                }
+                return Ok((*address, account));
+            }
+            let account = RpcClient::new(url.clone())
+                .get_account_with_commitment(address, CommitmentConfig::confirmed())
+                .with_context(|| format!("failed to clone {} from {}", address, url))?
+                .value
+                .ok_or_else(|| {
+                    anyhow!("failed to clone {}, {} has no such account", address, url)
+                })?;
+            write_dump(&path, address, &account)?;
+            Ok((*address, account))
+        }
+        AccountFixture::Dump(file) => read_dump(&base_dir.join(file)),
+    }
+}
+
+/// Reads an account in the json format of `solana account --output json`
+fn read_dump(path: &Path) -> Result<(Pubkey, Account)> {
+    let context = || format!("failed to read account {}", path.display());
+    let dump: RpcKeyedAccount =
+        serde_json::from_str(&fs::read_to_string(path).with_context(context)?)
+            .with_context(context)?;
+    let address = dump
+        .pubkey
+        .parse()
+        .map_err(|_| anyhow!("invalid pubkey `{}`", dump.pubkey))
+        .with_context(context)?;
+    let account = dump
+        .account
+        .decode()
+        .ok_or_else(|| anyhow!("the account data is not base58 or base64 encoded"))
+        .with_context(context)?;
+    Ok((address, account))
+}
+
+/// Writes an account in the json format of `solana account --output json`
+fn write_dump(path: &Path, address: &Pubkey, account: &Account) -> Result<()> {
+    let context = || format!("failed to cache account {} in {}", address, path.display());
+    let dump = RpcKeyedAccount {
+        pubkey: address.to_string(),
+        account: UiAccount::encode(
+            address,
+            account.clone(),
+            UiAccountEncoding::Base64,
+            None,
+            None,
+        ),
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(context)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&dump)? + "\n").with_context(context)
+}
+
+fn sequence<'a>(yaml: &'a Node, path: &str) -> Result<Vec<(String, &'a Node)>, ParseError> {
+    let items = yaml
+        .as_sequence()
+        .ok_or_else(|| ParseError::invalid_type(yaml, path, "a sequence"))?;
+    Ok(items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| (format!("{}[{}]", path, index), item))
+        .collect())
+}
+
+/// `{address, file}`
+fn parse_program(yaml: &Node, path: &str) -> Result<ProgramFixture, ParseError> {
+    check_fields(yaml, path, &["address", "file"])?;
+    Ok(ProgramFixture {
+        address: parse_pubkey(field(yaml, path, "address")?, &format!("{}.address", path))?,
+        file: string(field(yaml, path, "file")?, &format!("{}.file", path))?.into(),
+    })
+}
+
+/// `{address, lamports, owner, executable, data}`, where `data` is written like
+/// instruction data, or `dataFile` names a file with the raw data instead. Alternatively
+/// `{clone: {key, from, cache}}` or `{dump: file}`.
+fn parse_account(yaml: &Node, path: &str) -> Result<AccountFixture, ParseError> {
+    if let Some(clone) = yaml.get("clone") {
+        check_fields(yaml, path, &["clone"])?;
+        return parse_clone(clone, &format!("{}.clone", path));
+    }
+    if let Some(file) = yaml.get("dump") {
+        check_fields(yaml, path, &["dump"])?;
+        let file = string(file, &format!("{}.dump", path))?;
+        return Ok(AccountFixture::Dump(file.into()));
+    }
+    check_fields(
+        yaml,
+        path,
+        &[
+            "address",
+            "lamports",
+            "owner",
+            "executable",
+            "data",
+            "dataFile",
+        ],
+    )?;
+    let owner = match yaml.get("owner") {
+        Some(owner) => parse_pubkey(owner, &format!("{}.owner", path))?,
+        None => system_program::id(),
+    };
+    let executable = match yaml.get("executable") {
+        Some(executable) => executable.as_bool().ok_or_else(|| {
+            ParseError::invalid_type(executable, &format!("{}.executable", path), "a boolean")
+        })?,
+        None => false,
+    };
+    let data = match (yaml.get("data"), yaml.get("dataFile")) {
+        (Some(_), Some(_)) => {
+            return Err(ParseError::new(
+                yaml,
+                path,
+                ParseErrorKind::InvalidValue {
+                    value: "data and dataFile".to_string(),
+                    expected: "only one of data or dataFile".to_string(),
+                },
+            ))
+        }
+        (Some(data), None) => AccountData::Bytes(parse_data(data, &format!("{}.data", path))?),
+        (None, Some(file)) => {
+            AccountData::File(string(file, &format!("{}.dataFile", path))?.into())
+        }
+        (None, None) => AccountData::Bytes(vec![]),
+    };
+    Ok(AccountFixture::Inline {
+        address: parse_pubkey(field(yaml, path, "address")?, &format!("{}.address", path))?,
+        lamports: number(
+            field(yaml, path, "lamports")?,
+            &format!("{}.lamports", path),
+        )?,
+        owner,
+        executable,
+        data,
+    })
+}
+
+/// `{key, from, cache}`, where `from` is a cluster url or moniker such as `mainnet-beta`
+/// and `cache` defaults to `fixtures/<key>.json`
+fn parse_clone(yaml: &Node, path: &str) -> Result<AccountFixture, ParseError> {
+    check_fields(yaml, path, &["key", "from", "cache"])?;
+    let address = parse_pubkey(field(yaml, path, "key")?, &format!("{}.key", path))?;
+    let from = string(field(yaml, path, "from")?, &format!("{}.from", path))?;
+    let cache = match yaml.get("cache") {
+        Some(cache) => string(cache, &format!("{}.cache", path))?.into(),
+        None => Path::new(CACHE_DIR).join(format!("{}.json", address)),
+    };
+    Ok(AccountFixture::Clone {
+        address,
+        url: normalize_to_url_if_moniker(from),
+        cache,
+    })
+}
+
+fn string<'a>(yaml: &'a Node, path: &str) -> Result<&'a str, ParseError> {
+    yaml.as_str()
+        .ok_or_else(|| ParseError::invalid_type(yaml, path, "a string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for the files of one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("soltx-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let dir = test_dir("dump");
+        let path = dir.join(CACHE_DIR).join("account.json");
+        let address = Pubkey::new_unique();
+        let account = Account {
+            lamports: 1_000,
+            data: vec![1, 2, 3],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 7,
+        };
+        write_dump(&path, &address, &account).unwrap();
+        assert_eq!(read_dump(&path).unwrap(), (address, account));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_solana_account_output() {
+        let dir = test_dir("solana-account");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("account.json");
+        let address = Pubkey::new_unique();
+        fs::write(
+            &path,
+            format!(
+                r#"{{"pubkey": "{}", "account": {{"lamports": 5, "data": ["AQI=", "base64"],
+                    "owner": "11111111111111111111111111111111", "executable": true,
+                    "rentEpoch": 0}}}}"#,
+                address
+            ),
+        )
+        .unwrap();
+        let (read, account) = read_dump(&path).unwrap();
+        assert_eq!(read, address);
+        assert_eq!(account.lamports, 5);
+        assert_eq!(account.data, vec![1, 2]);
+        assert_eq!(account.owner, system_program::id());
+        assert!(account.executable);
+
+        fs::write(&path, r#"{"pubkey": "nope", "account": {}}"#).unwrap();
+        assert!(read_dump(&path).is_err());
+        assert!(read_dump(&dir.join("missing.json")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
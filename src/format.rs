@@ -0,0 +1,172 @@
+//! The file formats a transaction file may be written in
+
+use {
+    crate::{
+        error::FileError,
+        yaml::{self, Node},
+    },
+    std::{path::Path, str::FromStr},
+};
+
+pub const FORMATS: &[&str] = &["yaml", "json", "toml"];
+
+/// yaml files hold one transaction per yaml document. json and toml have no documents,
+/// so such a file holds a single document, or several under a top level `documents`
+/// key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// Picks the format by the file extension, yaml unless it is `.json` or `.toml`
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("json") => Format::Json,
+            Some("toml") => Format::Toml,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "yaml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            _ => anyhow::bail!(
+                "unknown format {}, expected one of {}",
+                s,
+                FORMATS.join(", ")
+            ),
+        }
+    }
+}
+
+/// Loads the documents of a transaction file
+pub fn load_documents(source: &str, format: Format) -> Result<Vec<Node>, FileError> {
+    let root = match format {
+        Format::Yaml => return yaml::load_from_str(source).map_err(FileError::Yaml),
+        Format::Json => serde_json::from_str::<Node>(source).map_err(FileError::Json)?,
+        Format::Toml => toml::from_str::<Node>(source).map_err(FileError::Toml)?,
+    };
+    match root.get("documents").and_then(Node::as_sequence) {
+        Some(documents) => Ok(documents.to_vec()),
+        None => Ok(vec![root]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            error::{ParseError, ParseErrorKind},
+            idl::Idls,
+            vars::Variables,
+            TxFile,
+        },
+    };
+
+    const PROGRAM_ID: &str = "tLSGV7BXFM2LfS3jwFyk5bAqDMisNQE4FUWPMxNnXJZ";
+
+    fn parse(source: &str, format: Format) -> Result<TxFile, FileError> {
+        TxFile::parse(
+            source,
+            format,
+            &mut Variables::default(),
+            &mut Idls::offline(""),
+        )
+    }
+
+    fn document_error(source: &str, format: Format) -> (usize, ParseError) {
+        match parse(source, format) {
+            Err(FileError::Document { index, error, .. }) => (index, error),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("parsed {}", source),
+        }
+    }
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(Format::from_path("a/b.json"), Format::Json);
+        assert_eq!(Format::from_path("b.toml"), Format::Toml);
+        assert_eq!(Format::from_path("b.yml"), Format::Yaml);
+        assert_eq!(Format::from_path("b"), Format::Yaml);
+    }
+
+    #[test]
+    fn json_documents() {
+        let document = format!(
+            r#"{{"vars": {{"amount": 7}}, "instructions": [{{"programId": "{}",
+                "accounts": [], "data": {{"u64": "$amount"}}}}]}}"#,
+            PROGRAM_ID
+        );
+        let tx_file = parse(&document, Format::Json).unwrap();
+        assert_eq!(tx_file.document_count, 1);
+        assert_eq!(
+            tx_file.documents[0].instructions[0].data,
+            7u64.to_le_bytes()
+        );
+
+        let source = format!(r#"{{"documents": [{0}, {0}]}}"#, document);
+        let tx_file = parse(&source, Format::Json).unwrap();
+        assert_eq!(tx_file.document_count, 2);
+        assert_eq!(tx_file.documents[1].index, 1);
+    }
+
+    #[test]
+    fn toml_documents() {
+        let source = format!(
+            "[[documents]]\ncommitment = \"processed\"\n\
+             [[documents.instructions]]\nprogramId = \"{0}\"\naccounts = []\n\
+             data = {{ hex = \"0102\" }}\n\
+             [[documents]]\n\
+             [[documents.instructions]]\nprogramId = \"{0}\"\naccounts = []\ndata = \"3\"\n",
+            PROGRAM_ID
+        );
+        let tx_file = parse(&source, Format::Toml).unwrap();
+        assert_eq!(tx_file.document_count, 2);
+        assert_eq!(tx_file.documents[0].instructions[0].data, vec![1, 2]);
+        assert_eq!(tx_file.documents[1].instructions[0].data, vec![3]);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let (index, error) = document_error(
+            r#"{"documents": [{"instructions": []}, {"instrctions": []}]}"#,
+            Format::Json,
+        );
+        assert_eq!(index, 1);
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::UnknownField { ref field, .. } if field == "instrctions"
+        ));
+        assert_eq!((error.line, error.column), (0, 0));
+
+        let source = format!(
+            "[[instructions]]\nprogramId = \"{}\"\naccounts = []\ndata = 1\nextra = 2\n",
+            PROGRAM_ID
+        );
+        let (_, error) = document_error(&source, Format::Toml);
+        assert_eq!(error.path, "instructions[0].extra");
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::UnknownField { ref field, .. } if field == "extra"
+        ));
+
+        assert!(matches!(
+            parse("{\"instructions\": [}", Format::Json),
+            Err(FileError::Json(_))
+        ));
+    }
+}
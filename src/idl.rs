@@ -0,0 +1,697 @@
+use {
+    crate::{
+        data::{number, parse_data},
+        error::{ParseError, ParseErrorKind},
+        parse::{check_fields, field, parse_account_key, parse_pubkey},
+        yaml::Node,
+    },
+    serde::Deserialize,
+    solana_sdk::{
+        hash::hashv,
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    },
+    std::{
+        collections::BTreeMap,
+        fs,
+        io::Read,
+        path::{Path, PathBuf},
+        rc::Rc,
+    },
+};
+
+/// The subset of an Anchor IDL needed to encode instructions
+#[derive(Debug, Deserialize)]
+pub struct Idl {
+    pub name: String,
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDefinition>,
+    /// Account types, which arguments may reference like `types`
+    #[serde(default)]
+    pub accounts: Vec<IdlTypeDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub accounts: Vec<IdlAccountItem>,
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum IdlAccountItem {
+    Account(IdlAccount),
+    /// A nested `Accounts` struct
+    Accounts(IdlAccounts),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlAccount {
+    pub name: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlAccounts {
+    pub name: String,
+    pub accounts: Vec<IdlAccountItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlTypeDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefinitionTy,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum IdlTypeDefinitionTy {
+    Struct { fields: Vec<IdlField> },
+    Enum { variants: Vec<IdlEnumVariant> },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlEnumFields>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum IdlEnumFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    F32,
+    F64,
+    Bytes,
+    String,
+    PublicKey,
+    Defined(String),
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+}
+
+/// Reads the data of an account, used for on-chain IDLs
+type Fetch<'a> = Box<dyn FnMut(&Pubkey) -> Result<Vec<u8>, String> + 'a>;
+
+/// The Anchor IDLs used by a transaction file, loaded once each.
+///
+/// An IDL is read from the file an instruction names with `idl`, relative to the
+/// transaction file, or else from the program's on-chain IDL account.
+pub struct Idls<'a> {
+    base_dir: PathBuf,
+    fetch: Fetch<'a>,
+    files: BTreeMap<PathBuf, Rc<Idl>>,
+    programs: BTreeMap<Pubkey, Rc<Idl>>,
+}
+
+impl<'a> Idls<'a> {
+    pub fn new(
+        base_dir: impl Into<PathBuf>,
+        fetch: impl FnMut(&Pubkey) -> Result<Vec<u8>, String> + 'a,
+    ) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            fetch: Box::new(fetch),
+            files: BTreeMap::new(),
+            programs: BTreeMap::new(),
+        }
+    }
+
+    /// IDLs that can only be loaded from files, for parsing without a cluster
+    pub fn offline(base_dir: impl Into<PathBuf>) -> Self {
+        Self::new(base_dir, |_| {
+            Err("on-chain IDLs need a cluster, give the IDL file with `idl`".to_string())
+        })
+    }
+
+    fn load_file(&mut self, yaml: &Node, path: &str) -> Result<Rc<Idl>, ParseError> {
+        let file = yaml
+            .as_str()
+            .ok_or_else(|| ParseError::invalid_type(yaml, path, "a path to an IDL"))?;
+        let file = self.base_dir.join(Path::new(file));
+        if let Some(idl) = self.files.get(&file) {
+            return Ok(idl.clone());
+        }
+        let idl = fs::read_to_string(&file)
+            .map_err(|err| err.to_string())
+            .and_then(|json| serde_json::from_str::<Idl>(&json).map_err(|err| err.to_string()))
+            .map_err(|err| {
+                ParseError::new(
+                    yaml,
+                    path,
+                    ParseErrorKind::Idl(format!("failed to load {}: {}", file.display(), err)),
+                )
+            })?;
+        let idl = Rc::new(idl);
+        self.files.insert(file, idl.clone());
+        Ok(idl)
+    }
+
+    fn load_onchain(
+        &mut self,
+        program_id: &Pubkey,
+        yaml: &Node,
+        path: &str,
+    ) -> Result<Rc<Idl>, ParseError> {
+        if let Some(idl) = self.programs.get(program_id) {
+            return Ok(idl.clone());
+        }
+        let address = idl_address(program_id);
+        let idl = (self.fetch)(&address)
+            .and_then(|data| decode_idl_account(&data))
+            .map_err(|err| {
+                ParseError::new(
+                    yaml,
+                    path,
+                    ParseErrorKind::Idl(format!(
+                        "failed to load the IDL of {} from {}: {}",
+                        program_id, address, err
+                    )),
+                )
+            })?;
+        let idl = Rc::new(idl);
+        self.programs.insert(*program_id, idl.clone());
+        Ok(idl)
+    }
+}
+
+/// The address of the account that `anchor idl init` writes a program's IDL to
+pub fn idl_address(program_id: &Pubkey) -> Pubkey {
+    let base = Pubkey::find_program_address(&[], program_id).0;
+    // unwrap OK because the seed is shorter than MAX_SEED_LEN
+    Pubkey::create_with_seed(&base, "anchor:idl", program_id).unwrap()
+}
+
+/// An IDL account holds an 8 byte discriminator, the authority, and the zlib compressed
+/// json IDL prefixed with its length as a u32
+fn decode_idl_account(data: &[u8]) -> Result<Idl, String> {
+    let header_len = 8 + 32 + 4;
+    if data.len() < header_len {
+        return Err("the account is too small to be an IDL account".to_string());
+    }
+    let mut len = [0; 4];
+    len.copy_from_slice(&data[header_len - 4..header_len]);
+    let compressed = data
+        .get(header_len..header_len + u32::from_le_bytes(len) as usize)
+        .ok_or_else(|| "the IDL is longer than its account".to_string())?;
+    let mut json = String::new();
+    flate2::read::ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .map_err(|err| err.to_string())?;
+    serde_json::from_str(&json).map_err(|err| err.to_string())
+}
+
+/// Whether `yaml` is an Anchor instruction, written with `program` and `instruction`
+pub fn is_anchor_instruction(yaml: &Node) -> bool {
+    yaml.get("program").is_some() && yaml.get("instruction").is_some()
+}
+
+/// Encodes an Anchor instruction from its IDL.
+///
+/// The data is the 8 byte sighash of the instruction followed by its borsh encoded
+/// `args`. `accounts` maps the IDL's account names to pubkeys, or to `{key, signer}`,
+/// and nested account structs to mappings of their own. The accounts are ordered and
+/// flagged as the IDL declares them.
+pub fn parse_anchor_instruction(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+    idls: &mut Idls,
+) -> Result<Instruction, ParseError> {
+    check_fields(
+        yaml,
+        path,
+        &["program", "instruction", "idl", "args", "accounts"],
+    )?;
+    let program_path = format!("{}.program", path);
+    let program_id = parse_pubkey(field(yaml, path, "program")?, &program_path)?;
+    let idl = match yaml.get("idl") {
+        Some(file) => idls.load_file(file, &format!("{}.idl", path))?,
+        None => idls.load_onchain(&program_id, yaml, path)?,
+    };
+    let name_path = format!("{}.instruction", path);
+    let name = field(yaml, path, "instruction")?;
+    let name = name
+        .as_str()
+        .ok_or_else(|| ParseError::invalid_type(name, &name_path, "a string"))?;
+    let instruction = idl
+        .instructions
+        .iter()
+        .find(|instruction| to_snake_case(&instruction.name) == to_snake_case(name))
+        .ok_or_else(|| {
+            ParseError::new(
+                yaml,
+                &name_path,
+                ParseErrorKind::Idl(format!(
+                    "the IDL of {} has no instruction `{}`",
+                    idl.name, name
+                )),
+            )
+        })?;
+
+    let mut data = sighash(&instruction.name).to_vec();
+    let args_path = format!("{}.args", path);
+    let args = yaml.get("args");
+    if let Some(args) = args {
+        let names = instruction
+            .args
+            .iter()
+            .map(|arg| &arg.name)
+            .collect::<Vec<_>>();
+        check_fields(args, &args_path, &names)?;
+    }
+    for arg in &instruction.args {
+        let arg_path = format!("{}.{}", args_path, arg.name);
+        let value = args
+            .and_then(|args| args.get(&arg.name))
+            .ok_or_else(|| missing(args.unwrap_or(yaml), &args_path, "argument", &arg.name))?;
+        encode(value, &arg.ty, &idl, &arg_path, &mut data)?;
+    }
+
+    let mut accounts = vec![];
+    let accounts_path = format!("{}.accounts", path);
+    // an instruction without accounts may leave out the section
+    if yaml.get("accounts").is_some() || !instruction.accounts.is_empty() {
+        parse_accounts(
+            field(yaml, path, "accounts")?,
+            &accounts_path,
+            &instruction.accounts,
+            signer_sources,
+            &mut accounts,
+        )?;
+    }
+    Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+    })
+}
+
+fn parse_accounts(
+    yaml: &Node,
+    path: &str,
+    items: &[IdlAccountItem],
+    signer_sources: &mut Vec<(Pubkey, String)>,
+    accounts: &mut Vec<AccountMeta>,
+) -> Result<(), ParseError> {
+    if yaml.as_mapping().is_none() {
+        return Err(ParseError::invalid_type(yaml, path, "a mapping"));
+    }
+    let names = items.iter().map(item_name).collect::<Vec<_>>();
+    check_fields(yaml, path, &names)?;
+    for item in items {
+        let name = item_name(item);
+        let item_path = format!("{}.{}", path, name);
+        let value = yaml
+            .get(name)
+            .ok_or_else(|| missing(yaml, path, "account", name))?;
+        match item {
+            IdlAccountItem::Account(account) => accounts.push(AccountMeta {
+                pubkey: parse_account_key(value, &item_path, signer_sources)?,
+                is_signer: account.is_signer,
+                is_writable: account.is_mut,
+            }),
+            IdlAccountItem::Accounts(group) => {
+                parse_accounts(value, &item_path, &group.accounts, signer_sources, accounts)?
+            }
+        }
+    }
+    Ok(())
+}
+
+fn item_name(item: &IdlAccountItem) -> &str {
+    match item {
+        IdlAccountItem::Account(account) => &account.name,
+        IdlAccountItem::Accounts(group) => &group.name,
+    }
+}
+
+/// Appends the borsh encoding of `yaml` as a value of type `ty`
+fn encode(
+    yaml: &Node,
+    ty: &IdlType,
+    idl: &Idl,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ParseError> {
+    match ty {
+        IdlType::Bool => out.push(
+            yaml.as_bool()
+                .ok_or_else(|| ParseError::invalid_type(yaml, path, "a boolean"))?
+                as u8,
+        ),
+        IdlType::U8 => out.extend(&number::<u8>(yaml, path)?.to_le_bytes()),
+        IdlType::I8 => out.extend(&number::<i8>(yaml, path)?.to_le_bytes()),
+        IdlType::U16 => out.extend(&number::<u16>(yaml, path)?.to_le_bytes()),
+        IdlType::I16 => out.extend(&number::<i16>(yaml, path)?.to_le_bytes()),
+        IdlType::U32 => out.extend(&number::<u32>(yaml, path)?.to_le_bytes()),
+        IdlType::I32 => out.extend(&number::<i32>(yaml, path)?.to_le_bytes()),
+        IdlType::U64 => out.extend(&number::<u64>(yaml, path)?.to_le_bytes()),
+        IdlType::I64 => out.extend(&number::<i64>(yaml, path)?.to_le_bytes()),
+        IdlType::U128 => out.extend(&number::<u128>(yaml, path)?.to_le_bytes()),
+        IdlType::I128 => out.extend(&number::<i128>(yaml, path)?.to_le_bytes()),
+        IdlType::F32 => out.extend(&(float(yaml, path)? as f32).to_le_bytes()),
+        IdlType::F64 => out.extend(&float(yaml, path)?.to_le_bytes()),
+        IdlType::String => {
+            let string = yaml
+                .as_str()
+                .ok_or_else(|| ParseError::invalid_type(yaml, path, "a string"))?;
+            out.extend(&(string.len() as u32).to_le_bytes());
+            out.extend(string.as_bytes());
+        }
+        IdlType::Bytes => {
+            let bytes = parse_data(yaml, path)?;
+            out.extend(&(bytes.len() as u32).to_le_bytes());
+            out.extend(bytes);
+        }
+        IdlType::PublicKey => out.extend(&parse_pubkey(yaml, path)?.to_bytes()),
+        IdlType::Option(ty) => {
+            if yaml.is_null() {
+                out.push(0);
+            } else {
+                out.push(1);
+                encode(yaml, ty, idl, path, out)?;
+            }
+        }
+        IdlType::Vec(ty) => {
+            let items = sequence(yaml, path)?;
+            out.extend(&(items.len() as u32).to_le_bytes());
+            for (index, item) in items.iter().enumerate() {
+                encode(item, ty, idl, &format!("{}[{}]", path, index), out)?;
+            }
+        }
+        // byte arrays may also be written like instruction data, e.g. `{hex: ...}`
+        IdlType::Array(ty, len) if matches!(**ty, IdlType::U8) && yaml.as_sequence().is_none() => {
+            let bytes = parse_data(yaml, path)?;
+            if bytes.len() != *len {
+                return Err(wrong_length(yaml, path, *len, bytes.len()));
+            }
+            out.extend(bytes);
+        }
+        IdlType::Array(ty, len) => {
+            let items = sequence(yaml, path)?;
+            if items.len() != *len {
+                return Err(wrong_length(yaml, path, *len, items.len()));
+            }
+            for (index, item) in items.iter().enumerate() {
+                encode(item, ty, idl, &format!("{}[{}]", path, index), out)?;
+            }
+        }
+        IdlType::Defined(name) => {
+            let definition = idl
+                .types
+                .iter()
+                .chain(&idl.accounts)
+                .find(|definition| &definition.name == name)
+                .ok_or_else(|| {
+                    ParseError::new(
+                        yaml,
+                        path,
+                        ParseErrorKind::Idl(format!("the IDL does not define the type `{}`", name)),
+                    )
+                })?;
+            match &definition.ty {
+                IdlTypeDefinitionTy::Struct { fields } => {
+                    encode_fields(yaml, fields, idl, path, out)?
+                }
+                IdlTypeDefinitionTy::Enum { variants } => {
+                    encode_variant(yaml, variants, idl, path, out)?
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_fields(
+    yaml: &Node,
+    fields: &[IdlField],
+    idl: &Idl,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ParseError> {
+    if yaml.as_mapping().is_none() {
+        return Err(ParseError::invalid_type(yaml, path, "a mapping"));
+    }
+    let names = fields.iter().map(|field| &field.name).collect::<Vec<_>>();
+    check_fields(yaml, path, &names)?;
+    for field in fields {
+        let value = yaml
+            .get(&field.name)
+            .ok_or_else(|| missing(yaml, path, "field", &field.name))?;
+        encode(
+            value,
+            &field.ty,
+            idl,
+            &format!("{}.{}", path, field.name),
+            out,
+        )?;
+    }
+    Ok(())
+}
+
+/// An enum is written as the name of a unit variant, or as `{Variant: fields}` with a
+/// mapping of named fields or a sequence of tuple fields
+fn encode_variant(
+    yaml: &Node,
+    variants: &[IdlEnumVariant],
+    idl: &Idl,
+    path: &str,
+    out: &mut Vec<u8>,
+) -> Result<(), ParseError> {
+    let (name, fields) = match (yaml.as_str(), yaml.as_mapping()) {
+        (Some(name), _) => (name, None),
+        (None, Some([(name, fields)])) => (
+            name.as_str()
+                .ok_or_else(|| ParseError::invalid_type(name, path, "a variant name"))?,
+            Some(fields),
+        ),
+        _ => {
+            return Err(ParseError::invalid_type(
+                yaml,
+                path,
+                "a variant name or a mapping with a single variant",
+            ))
+        }
+    };
+    let (index, variant) = variants
+        .iter()
+        .enumerate()
+        .find(|(_, variant)| variant.name == name)
+        .ok_or_else(|| {
+            ParseError::new(
+                yaml,
+                path,
+                ParseErrorKind::Idl(format!("unknown variant `{}`", name)),
+            )
+        })?;
+    out.push(index as u8);
+    let path = &format!("{}.{}", path, name);
+    match (&variant.fields, fields) {
+        (None, _) => {}
+        (Some(_), None) => {
+            return Err(ParseError::new(
+                yaml,
+                path,
+                ParseErrorKind::Idl(format!("variant `{}` needs fields", name)),
+            ))
+        }
+        (Some(IdlEnumFields::Named(named)), Some(fields)) => {
+            encode_fields(fields, named, idl, path, out)?
+        }
+        (Some(IdlEnumFields::Tuple(types)), Some(fields)) => {
+            let items = sequence(fields, path)?;
+            if items.len() != types.len() {
+                return Err(wrong_length(fields, path, types.len(), items.len()));
+            }
+            for (index, (item, ty)) in items.iter().zip(types).enumerate() {
+                encode(item, ty, idl, &format!("{}[{}]", path, index), out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Anchor's instruction discriminator, the first 8 bytes of the sha256 of
+/// `global:<snake_case name>`
+pub fn sighash(name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"global:".as_ref(), to_snake_case(name).as_bytes()]);
+    let mut sighash = [0; 8];
+    sighash.copy_from_slice(&hash.to_bytes()[..8]);
+    sighash
+}
+
+/// IDLs name instructions in camelCase, the programs in snake_case
+fn to_snake_case(name: &str) -> String {
+    let mut snake_case = String::new();
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if !snake_case.is_empty() {
+                snake_case.push('_');
+            }
+            snake_case.extend(c.to_lowercase());
+        } else {
+            snake_case.push(c);
+        }
+    }
+    snake_case
+}
+
+fn sequence<'a>(yaml: &'a Node, path: &str) -> Result<&'a [Node], ParseError> {
+    yaml.as_sequence()
+        .ok_or_else(|| ParseError::invalid_type(yaml, path, "a sequence"))
+}
+
+fn float(yaml: &Node, path: &str) -> Result<f64, ParseError> {
+    yaml.as_f64()
+        .ok_or_else(|| ParseError::invalid_type(yaml, path, "a number"))
+}
+
+fn missing(yaml: &Node, path: &str, what: &str, name: &str) -> ParseError {
+    ParseError::new(
+        yaml,
+        path,
+        ParseErrorKind::Idl(format!("missing {} `{}`", what, name)),
+    )
+}
+
+fn wrong_length(yaml: &Node, path: &str, expected: usize, found: usize) -> ParseError {
+    ParseError::new(
+        yaml,
+        path,
+        ParseErrorKind::Idl(format!("expected {} elements, found {}", expected, found)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::yaml::load_from_str,
+        flate2::{write::ZlibEncoder, Compression},
+        std::io::Write,
+    };
+
+    const IDL: &str = r#"{
+        "name": "counter",
+        "instructions": [{
+            "name": "initializeCounter",
+            "accounts": [
+                {"name": "counter", "isMut": true, "isSigner": true},
+                {"name": "nested", "accounts": [
+                    {"name": "authority", "isMut": false, "isSigner": true}
+                ]}
+            ],
+            "args": [
+                {"name": "start", "type": "u64"},
+                {"name": "label", "type": {"option": "string"}},
+                {"name": "config", "type": {"defined": "Config"}}
+            ]
+        }],
+        "types": [{
+            "name": "Config",
+            "type": {"kind": "struct", "fields": [
+                {"name": "step", "type": "i16"},
+                {"name": "seed", "type": {"array": ["u8", 2]}},
+                {"name": "mode", "type": {"defined": "Mode"}}
+            ]}
+        }, {
+            "name": "Mode",
+            "type": {"kind": "enum", "variants": [{"name": "Up"}, {"name": "Down"}]}
+        }]
+    }"#;
+
+    /// An IDL account as Anchor writes it: discriminator, authority, length and the
+    /// zlib compressed json
+    fn idl_account() -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(IDL.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut data = vec![0; 8 + 32];
+        data.extend(&(compressed.len() as u32).to_le_bytes());
+        data.extend(compressed);
+        data
+    }
+
+    #[test]
+    fn sighash_is_anchors_discriminator() {
+        assert_eq!(
+            sighash("initialize"),
+            [175, 175, 109, 31, 13, 152, 155, 237]
+        );
+        // camelCase names are hashed in snake_case
+        assert_eq!(
+            sighash("initializeMint"),
+            [209, 42, 195, 4, 129, 85, 209, 44]
+        );
+    }
+
+    #[test]
+    fn encodes_an_instruction_from_the_onchain_idl() {
+        let program_id = Pubkey::new_unique();
+        let (counter, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut idls = Idls::new("", |address: &Pubkey| {
+            assert_eq!(*address, idl_address(&program_id));
+            Ok(idl_account())
+        });
+        let yaml = load_from_str(&format!(
+            "program: {}\n\
+             instruction: initializeCounter\n\
+             args: {{start: 5, label: hi, config: {{step: -2, seed: {{hex: abcd}}, mode: Down}}}}\n\
+             accounts: {{counter: {{key: {}, signer: counter.json}}, nested: {{authority: {}}}}}",
+            program_id, counter, authority
+        ))
+        .unwrap()
+        .remove(0);
+        let mut signer_sources = vec![];
+        let instruction =
+            parse_anchor_instruction(&yaml, "", &mut signer_sources, &mut idls).unwrap();
+
+        let mut data = sighash("initializeCounter").to_vec();
+        data.extend(&5u64.to_le_bytes());
+        data.extend(&[1, 2, 0, 0, 0, b'h', b'i']);
+        data.extend(&(-2i16).to_le_bytes());
+        data.extend(&[0xab, 0xcd, 1]);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.data, data);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                AccountMeta::new(counter, true),
+                AccountMeta::new_readonly(authority, true)
+            ]
+        );
+        assert_eq!(signer_sources, vec![(counter, "counter.json".to_string())]);
+    }
+}
@@ -0,0 +1,39 @@
+//! Builds, signs and sends Solana transactions described in transaction files.
+//!
+//! A transaction file holds one transaction per yaml document, or lists them under
+//! `documents` in json and toml. Parsing a file into a [`TxFile`] needs no cluster, so
+//! the instructions can be inspected or used in tests:
+//!
+//! ```no_run
+//! let tx_file = soltx::TxFile::load("transactions.yaml")?;
+//! for document in &tx_file.documents {
+//!     println!("{:?}", document.instructions);
+//! }
+//! # Ok::<(), soltx::error::FileError>(())
+//! ```
+//!
+//! [`builder::build_transaction`] compiles a document into a signed `Transaction` and
+//! [`sender`] sends or simulates it.
+
+pub mod bank;
+pub mod blob;
+pub mod builder;
+pub mod check;
+pub mod compute_budget;
+pub mod data;
+pub mod decode;
+pub mod error;
+pub mod expect;
+pub mod fixtures;
+pub mod format;
+pub mod idl;
+pub mod parse;
+mod programs;
+pub mod report;
+pub mod sender;
+pub mod signers;
+pub mod spec;
+pub mod vars;
+pub mod yaml;
+
+pub use parse::{Document, TxFile};
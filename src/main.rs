@@ -1,37 +1,121 @@
 use {
-    clap::{App, Arg},
-    solana_clap_utils::{input_validators::is_valid_signer, keypair::DefaultSigner},
+    clap::{App, AppSettings, Arg, ArgMatches, SubCommand},
+    solana_clap_utils::{
+        fee_payer::{fee_payer_arg, FEE_PAYER_ARG},
+        input_parsers::{pubkey_of, value_of},
+        input_validators::{
+            is_parsable, is_url, is_url_or_moniker, is_valid_pubkey, is_valid_signer,
+            normalize_to_url_if_moniker,
+        },
+        nonce::{nonce_authority_arg, NONCE_ARG, NONCE_AUTHORITY_ARG},
+        offline::{blockhash_arg, sign_only_arg, BLOCKHASH_ARG, SIGN_ONLY_ARG},
+    },
     solana_client::rpc_client::RpcClient,
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
     solana_sdk::{
-        commitment_config::CommitmentConfig,
-        signature::{Signature, Signer},
+        hash::Hash,
+        signature::{Keypair, Signature, Signer},
         transaction::Transaction,
     },
-    std::{process::exit, sync::Arc},
+    soltx::{
+        bank::BankSender,
+        blob::{self, Encoding},
+        builder::{self, Defaults, Signers},
+        check::Checker,
+        compute_budget, decode,
+        expect::{self, Expectation},
+        format::{self, Format},
+        idl::Idls,
+        parse::{self, ComputeBudget, NonceSpec, SendOptions},
+        report::{self, Report},
+        sender::{self, RpcSender, Sender},
+        signers,
+        vars::{self, Variables},
+        Document, TxFile,
+    },
+    std::{io::Write, path::Path, sync::Arc},
 };
 
-use std::{fs};
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+const COMMITMENT_LEVELS: &[&str] = &["processed", "confirmed", "finalized"];
 
-use anyhow::Result;
-use solana_client::rpc_config::RpcSendTransactionConfig;
-use solana_sdk::instruction::{AccountMeta, Instruction};
-use yaml_rust::{Yaml, YamlLoader};
 struct Config {
-    commitment_config: CommitmentConfig,
-    default_signer: Box<dyn Signer>,
+    send_options: SendOptions,
+    default_signer_path: String,
     json_rpc_url: String,
+    websocket_url: String,
 }
 
 fn main() -> Result<()> {
-    let matches = App::new("soltx")
-        .arg(Arg::with_name("FILE").required(true))
+    let app_matches = App::new("soltx")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .arg(
+            Arg::with_name("FILE")
+                .required(true)
+                .help("Transaction file to send"),
+        )
+        .arg(output_arg())
+        .subcommand(
+            SubCommand::with_name("simulate")
+                .about("Simulate the transactions and print their logs without sending them")
+                .arg(Arg::with_name("FILE").required(true))
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Print the transactions encoded, signed by the signers that are available")
+                .arg(Arg::with_name("FILE").required(true))
+                .arg(encoding_arg().default_value("base64"))
+                .arg(output_file_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("sign")
+                .about("Add signatures to encoded transactions")
+                .arg(blob_arg())
+                .arg(encoding_arg().help("Encoding of the transactions [default: the input's]"))
+                .arg(output_file_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("submit")
+                .about("Send fully signed encoded transactions")
+                .arg(blob_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about(
+                    "Check the transactions without the cluster and list every problem, \
+                     pass --keypair or --fee-payer as a PUBKEY when its keypair is not at hand",
+                )
+                .arg(Arg::with_name("FILE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about(
+                    "Run the transactions against an in-process bank set up with the programs \
+                     and fixtures of the file, signing with a new keypair unless --keypair is \
+                     given. The bank returns no logs, so logsContain expectations are \
+                     rejected",
+                )
+                .arg(Arg::with_name("FILE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("decode")
+                .about("Print transactions as transaction files")
+                .arg(blob_arg().help(
+                    "Signature of a confirmed transaction or an encoded transaction, \
+                     a file with one per line, or - to read them from stdin",
+                )),
+        )
         .arg({
             let arg = Arg::with_name("config_file")
                 .short("C")
                 .long("config")
                 .value_name("CONFIG_PATH")
                 .takes_value(true)
+                .global(true)
                 .help("Configuration file to use");
             if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
                 arg.default_value(&config_file)
@@ -39,6 +123,157 @@ fn main() -> Result<()> {
                 arg
             }
         })
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .short("u")
+                .long("url")
+                .value_name("URL_OR_MONIKER")
+                .takes_value(true)
+                .global(true)
+                .validator(is_url_or_moniker)
+                .help(
+                    "URL for Solana's JSON RPC or moniker (or their first letter): \
+                     [mainnet-beta, testnet, devnet, localhost]",
+                ),
+        )
+        .arg(
+            Arg::with_name("websocket_url")
+                .long("ws")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .validator(is_url)
+                .help("WebSocket URL for the solana cluster, used to wait for confirmations"),
+        )
+        .arg(
+            Arg::with_name("commitment")
+                .long("commitment")
+                .value_name("COMMITMENT_LEVEL")
+                .takes_value(true)
+                .possible_values(COMMITMENT_LEVELS)
+                .global(true)
+                .help("Commitment to wait for [default: confirmed]"),
+        )
+        .arg(
+            Arg::with_name("skip_preflight")
+                .long("skip-preflight")
+                .global(true)
+                .help("Skip the preflight simulation of the RPC node"),
+        )
+        .arg(
+            Arg::with_name("preflight_commitment")
+                .long("preflight-commitment")
+                .value_name("COMMITMENT_LEVEL")
+                .takes_value(true)
+                .possible_values(COMMITMENT_LEVELS)
+                .global(true)
+                .help("Commitment of the preflight simulation [default: the --commitment]"),
+        )
+        .arg(
+            Arg::with_name("no_wait")
+                .long("no-wait")
+                .global(true)
+                .help("Print the signature right after sending, without waiting for confirmation"),
+        )
+        .arg(
+            Arg::with_name("max_attempts")
+                .long("max-attempts")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<u32>)
+                .global(true)
+                .help(
+                    "Send a dropped transaction again, up to this many times in all [default: 1]",
+                ),
+        )
+        .arg(
+            Arg::with_name("retry_backoff")
+                .long("retry-backoff")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .global(true)
+                .help(
+                    "Wait before the first retry, doubled for every retry after it [default: 1000]",
+                ),
+        )
+        .arg(
+            Arg::with_name("signer")
+                .long("signer")
+                .value_name("KEYPAIR or PUBKEY=SIGNATURE")
+                .validator(signers::is_valid_signer_source)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help(
+                    "Filepath or URL to a keypair of an additional signer, or a \
+                     public-key/signature pair from an offline signer, may be repeated",
+                ),
+        )
+        .arg(blockhash_arg().global(true))
+        .arg(sign_only_arg().global(true))
+        .arg(
+            // unlike solana-clap-utils' nonce arg this does not require --blockhash,
+            // the nonce is fetched from the account instead
+            Arg::with_name(NONCE_ARG.name)
+                .long(NONCE_ARG.long)
+                .takes_value(true)
+                .value_name("PUBKEY")
+                .validator(is_valid_pubkey)
+                .global(true)
+                .help(NONCE_ARG.help),
+        )
+        .arg(nonce_authority_arg().requires(NONCE_ARG.name).global(true))
+        .arg(
+            Arg::with_name("compute_unit_limit")
+                .long("compute-unit-limit")
+                .value_name("UNITS")
+                .takes_value(true)
+                .validator(is_compute_unit_limit)
+                .global(true)
+                .help(
+                    "Request this many compute units, or with `auto` the units consumed in a \
+                     simulation plus 10%",
+                ),
+        )
+        .arg(
+            Arg::with_name("compute_unit_price")
+                .long("compute-unit-price")
+                .value_name("MICROLAMPORTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .global(true)
+                .help("Pay a priority fee of this many micro-lamports per compute unit"),
+        )
+        .arg(
+            fee_payer_arg()
+                .validator(signers::is_valid_signer_source)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("set")
+                .long("set")
+                .value_name("NAME=VALUE")
+                .validator(vars::is_assignment)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Set a variable, overriding its value in the file, may be repeated"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(format::FORMATS)
+                .global(true)
+                .help(
+                    "Format of the transaction file, or of the output of decode \
+                     [default: by the file extension, yaml for decode]",
+                ),
+        )
         .arg(
             Arg::with_name("keypair")
                 .long("keypair")
@@ -49,8 +284,14 @@ fn main() -> Result<()> {
                 .help("Filepath or URL to a keypair [default: client keypair]"),
         )
         .get_matches();
+    let (subcommand, matches) = match app_matches.subcommand() {
+        (name, Some(sub_matches)) => (name, sub_matches),
+        _ => ("send", &app_matches),
+    };
 
     let mut wallet_manager: Option<Arc<RemoteWalletManager>> = None;
+    // a built transaction is signed by whichever signers are available, like --sign-only
+    let sign_only = matches.is_present(SIGN_ONLY_ARG.name) || subcommand == "build";
 
     let config = {
         let cli_config = if let Some(config_file) = matches.value_of("config_file") {
@@ -59,106 +300,490 @@ fn main() -> Result<()> {
             solana_cli_config::Config::default()
         };
 
-        let default_signer = DefaultSigner {
-            path: matches
-                .value_of(&"keypair")
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| cli_config.keypair_path.clone()),
-            arg_name: "keypair".to_string(),
+        let default_signer_path = matches
+            .value_of(&"keypair")
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| cli_config.keypair_path.clone());
+
+        let json_rpc_url = matches
+            .value_of("json_rpc_url")
+            .map(normalize_to_url_if_moniker)
+            .unwrap_or_else(|| cli_config.json_rpc_url.clone());
+        // same precedence as the solana cli: an explicit --ws, then the url derived from an
+        // explicit --url, then the config file
+        let websocket_url = match matches.value_of("websocket_url") {
+            Some(websocket_url) => websocket_url.to_string(),
+            None if matches.is_present("json_rpc_url") || cli_config.websocket_url.is_empty() => {
+                solana_cli_config::Config::compute_websocket_url(&json_rpc_url)
+            }
+            None => cli_config.websocket_url.clone(),
         };
 
         Config {
-            json_rpc_url: matches
-                .value_of("json_rpc_url")
-                .unwrap_or(&cli_config.json_rpc_url)
-                .to_string(),
-            default_signer: default_signer
-                .signer_from_path(&matches, &mut wallet_manager)
-                .unwrap_or_else(|err| {
-                    eprintln!("error: {}", err);
-                    exit(1);
-                }),
-            commitment_config: CommitmentConfig::confirmed(),
+            json_rpc_url,
+            websocket_url,
+            default_signer_path,
+            send_options: SendOptions {
+                commitment: matches
+                    .value_of("commitment")
+                    .and_then(parse::parse_commitment),
+                skip_preflight: flag(matches, "skip_preflight"),
+                preflight_commitment: matches
+                    .value_of("preflight_commitment")
+                    .and_then(parse::parse_commitment),
+                no_wait: flag(matches, "no_wait"),
+                max_attempts: value_of(matches, "max_attempts"),
+                retry_backoff: value_of(matches, "retry_backoff"),
+            },
         }
     };
 
     let rpc_client = RpcClient::new(config.json_rpc_url.clone());
 
+    let blockhash = value_of::<Hash>(matches, BLOCKHASH_ARG.name);
+    let cli_nonce = pubkey_of(matches, NONCE_ARG.name).map(|account| NonceSpec {
+        account,
+        authority: matches
+            .value_of(NONCE_AUTHORITY_ARG.name)
+            .map(|s| s.to_string()),
+        create: None,
+        withdraw: None,
+    });
+    let cli_compute_budget = ComputeBudget {
+        unit_limit: matches
+            .value_of("compute_unit_limit")
+            .and_then(parse::parse_compute_unit_limit),
+        unit_price: value_of(matches, "compute_unit_price"),
+    };
+
+    let format = matches
+        .value_of("format")
+        .map(str::parse::<Format>)
+        .transpose()?;
+    let blobs = blob::read_blobs(matches.values_of("BLOB").into_iter().flatten())?;
+    match subcommand {
+        "decode" => {
+            let mut transactions = vec![];
+            for blob in &blobs {
+                let transaction = match blob::decode(blob) {
+                    Ok((transaction, _)) => transaction,
+                    Err(err) => {
+                        let signature = blob
+                            .parse::<Signature>()
+                            .map_err(|_| anyhow!("{}: not a signature, and {}", blob, err))?;
+                        sender::fetch_transaction(&rpc_client, &signature, &config.send_options)?
+                    }
+                };
+                transactions.push(transaction);
+            }
+            let format = format.unwrap_or(Format::Yaml);
+            print!("{}", decode::transactions_to_string(&transactions, format)?);
+            return Ok(());
+        }
+        "submit" => {
+            for blob in &blobs {
+                let (transaction, _) = blob::decode(blob)?;
+                let absent = builder::absent_signers(&transaction);
+                if !absent.is_empty() {
+                    bail!(
+                        "transaction {} is missing the signatures of {}",
+                        transaction.signatures[0],
+                        absent
+                            .iter()
+                            .map(|pubkey| pubkey.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                let signature = sender::send_transaction(
+                    &transaction,
+                    &rpc_client,
+                    &config.websocket_url,
+                    &config.send_options,
+                )?;
+                println!("{}", signature);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let defaults = Defaults {
+        fee_payer: matches.value_of(FEE_PAYER_ARG.name).map(|s| s.to_string()),
+        nonce: cli_nonce,
+        compute_budget: cli_compute_budget,
+    };
+    if subcommand == "check" {
+        // unwrap OK cause FILE is a required arg
+        let path = matches.value_of("FILE").unwrap();
+        let source =
+            fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        let mut vars = Variables::with_overrides(matches.values_of("set").into_iter().flatten());
+        let format = format.unwrap_or_else(|| Format::from_path(path));
+        // a check only needs the pubkeys of the signers, so none is loaded
+        let checker = Checker {
+            default_signer: &config.default_signer_path,
+            signer_sources: signers::signer_sources(matches),
+            defaults: &defaults,
+        };
+        let problem_count = checker
+            .check_file(
+                &source,
+                format,
+                Path::new(path).parent().unwrap_or_else(|| Path::new("")),
+                &mut vars,
+                &mut |origin, problem| println!("{}: {}: {}", path, origin, problem),
+            )
+            .with_context(|| format!("failed to parse {}", path))?;
+        if problem_count > 0 {
+            bail!("found {} problems in {}", problem_count, path);
+        }
+        return Ok(());
+    }
+
+    // the default signer is only loaded once it is needed, decode and submit never sign
+    let default_signer: Box<dyn Signer> = if subcommand == "test" && !matches.is_present("keypair")
+    {
+        // the bank of a test funds whichever keypair signs, so none has to exist
+        Box::new(Keypair::new())
+    } else {
+        signers::load_signer(
+            &config.default_signer_path,
+            "keypair",
+            sign_only,
+            matches,
+            &mut wallet_manager,
+        )?
+    };
+    let mut signers = vec![default_signer];
+    signers::add_cli_signers(&mut signers, sign_only, matches, &mut wallet_manager)?;
+    if subcommand == "sign" {
+        let encoding = matches
+            .value_of("encoding")
+            .map(str::parse::<Encoding>)
+            .transpose()?;
+        let mut signed = vec![];
+        for blob in &blobs {
+            let (mut transaction, input_encoding) = blob::decode(blob)?;
+            builder::add_signatures(&mut transaction, &signers)?;
+            signed.push(blob::encode(
+                &transaction,
+                encoding.unwrap_or(input_encoding),
+            )?);
+        }
+        return write_output(matches.value_of("output_file"), &signed);
+    }
+    let mut signers = Signers::new(signers, |source| {
+        signers::load_signer(source, "signer", sign_only, matches, &mut wallet_manager)
+    });
+
     // unwrap OK cause FILE is a required arg
     if let Some(path) = matches.value_of("FILE") {
-        let file_content = fs::read_to_string(path)?;
-        let content_as_yaml = YamlLoader::load_from_str(&file_content)?;
-        let signature = send_transaction(
-            content_as_yaml.get(0),
-            config.default_signer.as_ref(),
-            &rpc_client,
-            config.commitment_config,
-        )?;
-        println!("{}", signature);
+        let source =
+            fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        // parse everything up front so a typo never leaves the file half sent
+        let mut vars = Variables::with_overrides(matches.values_of("set").into_iter().flatten());
+        let format = format.unwrap_or_else(|| Format::from_path(path));
+        // IDL, program and fixture files are relative to the transaction file
+        let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        // a test never touches the cluster
+        let mut idls = if subcommand == "test" {
+            Idls::offline(base_dir)
+        } else {
+            Idls::new(base_dir, |address| {
+                rpc_client
+                    .get_account_data(address)
+                    .map_err(|err| err.to_string())
+            })
+        };
+        let tx_file = TxFile::parse(&source, format, &mut vars, &mut idls)
+            .with_context(|| format!("failed to parse {}", path))?;
+        let document_count = tx_file.document_count;
+        let context = |index: usize| {
+            move || {
+                format!(
+                    "document {} of {} in {} failed",
+                    index + 1,
+                    document_count,
+                    path
+                )
+            }
+        };
+        let mut sender: Box<dyn Sender + '_> = if subcommand == "test" {
+            // the bank funds the signers it starts with, so every document's signers are
+            // resolved up front
+            for document in &tx_file.documents {
+                builder::resolve_signers(document, &mut signers, &defaults)
+                    .with_context(context(document.index))?;
+            }
+            let funded = signers.pubkeys();
+            Box::new(
+                BankSender::start(&tx_file.fixtures, base_dir, &funded)
+                    .context("failed to start the bank")?,
+            )
+        } else {
+            Box::new(RpcSender {
+                rpc_client: &rpc_client,
+                websocket_url: &config.websocket_url,
+                blockhash,
+                sign_only,
+            })
+        };
+        // fail before anything is sent rather than on the first document that expects logs
+        if !sender.returns_logs() {
+            let expects_logs = |document: &&Document| {
+                document
+                    .expectations
+                    .iter()
+                    .any(|expectation| matches!(expectation, Expectation::LogsContain(_)))
+            };
+            if let Some(document) = tx_file.documents.iter().find(expects_logs) {
+                bail!(
+                    "document {} of {} in {} expects logs with logsContain, which the \
+                     in-process bank of soltx test does not return",
+                    document.index + 1,
+                    document_count,
+                    path
+                );
+            }
+        }
+        let encoding = matches
+            .value_of("encoding")
+            .map(str::parse::<Encoding>)
+            .transpose()?;
+        let json_output = matches.value_of("output") == Some("json");
+        let mut built = vec![];
+        let mut failures = 0;
+        // each yaml document is sent as its own transaction, in file order
+        for document in &tx_file.documents {
+            let context = context(document.index);
+            // the document's options take precedence over the command line
+            let send_options = document.options.or(&config.send_options);
+            let builder::Built {
+                mut transaction,
+                message,
+                auto_unit_limit,
+            } = builder::build_transaction(
+                document,
+                &mut signers,
+                &defaults,
+                sender.as_mut(),
+                &send_options,
+                sign_only,
+            )
+            .with_context(context)?;
+            if let Some((units, consumed)) = auto_unit_limit {
+                eprintln!(
+                    "Compute unit limit: {} ({} units consumed in simulation plus {}%)",
+                    units,
+                    consumed,
+                    compute_budget::AUTO_MARGIN_PERCENT
+                );
+            }
+            let mut resign = |blockhash| {
+                builder::sign_transaction(message.clone(), signers.signers(), blockhash, sign_only)
+            };
+            if let Some(encoding) = encoding {
+                built.push(blob::encode(&transaction, encoding).with_context(context)?);
+            } else if sign_only {
+                print_sign_only(&transaction);
+            } else if subcommand == "simulate" {
+                let simulation = sender
+                    .simulate(&transaction, &send_options)
+                    .with_context(context)?;
+                if json_output {
+                    print_report(&Report::simulated(&transaction, &simulation))?;
+                    if let Some(err) = &simulation.err {
+                        return Err(anyhow!("simulation failed: {}", err)).with_context(context);
+                    }
+                } else {
+                    print_simulation(&simulation).with_context(context)?;
+                }
+            } else if subcommand == "test" {
+                // a failed document does not stop the test, later ones may not depend on it
+                let name = format!("document {} of {}", document.index + 1, document_count);
+                match expect::send_expecting(
+                    sender.as_mut(),
+                    &mut transaction,
+                    document,
+                    &send_options,
+                    &mut resign,
+                    &mut |_| {},
+                ) {
+                    Ok(signature) => println!("{}: ok {}", name, signature),
+                    Err(err) => {
+                        println!("{}: failed: {:#}", name, err);
+                        failures += 1;
+                    }
+                }
+            } else if json_output {
+                let (report, result) = sender::send_with_report(
+                    &mut transaction,
+                    document,
+                    sender.as_mut(),
+                    &rpc_client,
+                    &send_options,
+                    &mut resign,
+                    &mut print_attempt,
+                );
+                // a failed transaction is reported before its error is returned
+                print_report(&report)?;
+                result.with_context(context)?;
+            } else {
+                println!("{:?}", &transaction.signatures);
+                let signature = expect::send_expecting(
+                    sender.as_mut(),
+                    &mut transaction,
+                    document,
+                    &send_options,
+                    &mut resign,
+                    &mut print_attempt,
+                )
+                .with_context(context)?;
+                println!("{}", signature);
+            }
+        }
+        if failures > 0 {
+            bail!(
+                "{} of {} documents failed in {}",
+                failures,
+                document_count,
+                path
+            );
+        }
+        if subcommand == "build" {
+            write_output(matches.value_of("output_file"), &built)?;
+        }
     }
     Ok(())
 }
 
-fn send_transaction(
-    yaml: Option<&Yaml>,
-    signer: &dyn Signer,
-    rpc_client: &RpcClient,
-    commitment_config: CommitmentConfig,
-) -> Result<Signature> {
-    let instructions = match yaml {
-        None => vec![],
-        Some(v) =>     v
-        .as_vec()
-        .unwrap()
-        .iter()
-        .map(|x| yaml_to_instruction(x))
-        .collect::<Vec<Instruction>>()
-    };
-    
-    let mut transaction =
-        Transaction::new_with_payer(instructions.as_slice(), Some(&signer.pubkey()));
-    let (recent_blockhash, _fee_calculator) = rpc_client.get_recent_blockhash()?;
-
-    transaction.try_sign(&vec![signer], recent_blockhash)?;
-
-    println!("{:?}", &transaction.signatures);
-
-    let signature = rpc_client.send_and_confirm_transaction_with_spinner_and_config(
-        &transaction,
-        commitment_config,
-        RpcSendTransactionConfig {
-            skip_preflight: true,
-            preflight_commitment: None,
-            encoding: None
+fn encoding_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("encoding")
+        .long("output")
+        .value_name("ENCODING")
+        .takes_value(true)
+        .possible_values(blob::ENCODINGS)
+        .help("Encoding of the transactions")
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .long("output")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .possible_values(report::OUTPUTS)
+        .default_value("text")
+        .help("Print a json object per transaction, with its outcome, logs and signers")
+}
+
+fn output_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output_file")
+        .long("output-file")
+        .short("o")
+        .value_name("PATH")
+        .takes_value(true)
+        .help("Write the transactions to a file instead of stdout, one per line")
+}
+
+fn blob_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("BLOB")
+        .required(true)
+        .multiple(true)
+        .help("Encoded transaction, a file with one per line, or - to read them from stdin")
+}
+
+fn write_output(path: Option<&str>, lines: &[String]) -> Result<()> {
+    let mut output: Box<dyn Write> = match path {
+        Some(path) => {
+            Box::new(fs::File::create(path).with_context(|| format!("failed to create {}", path))?)
         }
-    )?;
-    Ok(signature)
+        None => Box::new(std::io::stdout()),
+    };
+    for line in lines {
+        writeln!(output, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn is_compute_unit_limit(limit: String) -> Result<(), String> {
+    parse::parse_compute_unit_limit(&limit)
+        .map(|_| ())
+        .ok_or_else(|| {
+            format!(
+                "expected a number of compute units or auto, found {}",
+                limit
+            )
+        })
 }
 
-fn yaml_to_account_meta(yaml: &Yaml) -> AccountMeta {
-    AccountMeta {
-        pubkey: yaml["key"].as_str().unwrap().parse().unwrap(),
-        is_signer: yaml["isSigner"].as_bool().unwrap(),
-        is_writable: yaml["isWritable"].as_bool().unwrap(),
+/// Flags can only be set on the command line, an absent flag leaves the option unset
+fn flag(matches: &ArgMatches, name: &str) -> Option<bool> {
+    if matches.is_present(name) {
+        Some(true)
+    } else {
+        None
     }
 }
 
-fn yaml_to_instruction(yaml: &Yaml) -> Instruction {
-    let data = yaml["data"]
-        .as_str()
-        .unwrap()
-        .split(',')
-        .map(|x| x.parse::<u8>().unwrap())
-        .collect();
-    let accounts = yaml["accounts"]
-        .as_vec()
-        .unwrap()
+/// Prints the signatures in the format of the solana cli's `--sign-only`, ready to be
+/// passed back with `--signer PUBKEY=SIGNATURE`
+fn print_sign_only(transaction: &Transaction) {
+    let signatures = transaction
+        .message
+        .account_keys
         .iter()
-        .map(|x| yaml_to_account_meta(x))
-        .collect();
-    Instruction {
-        program_id: yaml["programId"].as_str().unwrap().parse().unwrap(),
-        data,
-        accounts,
+        .zip(&transaction.signatures);
+    println!();
+    println!("Blockhash: {}", transaction.message.recent_blockhash);
+    let (present, absent): (Vec<_>, Vec<_>) =
+        signatures.partition(|(_, signature)| **signature != Signature::default());
+    if !present.is_empty() {
+        println!("Signers (Pubkey=Signature):");
+        for (pubkey, signature) in present {
+            println!("  {}={}", pubkey, signature);
+        }
+    }
+    if !absent.is_empty() {
+        println!("Absent Signers (Pubkey):");
+        for (pubkey, _) in absent {
+            println!("  {}", pubkey);
+        }
     }
 }
+
+fn print_simulation(simulation: &sender::Simulation) -> Result<()> {
+    println!("Logs:");
+    for log in &simulation.logs {
+        println!("  {}", log);
+    }
+    println!("Units consumed: {}", simulation.units_consumed);
+    match &simulation.err {
+        Some(err) => bail!("simulation failed: {}", err),
+        None => {
+            println!("Simulation succeeded");
+            Ok(())
+        }
+    }
+}
+
+/// Reports the attempts of a transaction that may be sent more than once on stderr
+fn print_attempt(attempt: &sender::Attempt) {
+    if attempt.max_attempts == 1 {
+        return;
+    }
+    let prefix = format!("attempt {} of {}", attempt.number, attempt.max_attempts);
+    match attempt.previous_error {
+        None => eprintln!("{}: sending {}", prefix, attempt.signature),
+        Some(err) if attempt.resigned => eprintln!(
+            "{}: {}, signed again with a new blockhash as {}",
+            prefix, err, attempt.signature
+        ),
+        Some(err) => eprintln!("{}: {}, sending {} again", prefix, err, attempt.signature),
+    }
+}
+
+fn print_report(report: &Report) -> Result<()> {
+    println!("{}", serde_json::to_string(report)?);
+    Ok(())
+}
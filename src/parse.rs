@@ -0,0 +1,533 @@
+use {
+    crate::{
+        data::number,
+        error::{FileError, ParseError, ParseErrorKind},
+        expect::{self, Expectation},
+        fixtures::Fixtures,
+        format::{self, Format},
+        idl::{self, Idls},
+        programs,
+        spec::{AddressSpec, DocumentSpec, InstructionSpec},
+        vars::Variables,
+        yaml::{from_node, Node, Value},
+    },
+    solana_sdk::{
+        commitment_config::{CommitmentConfig, CommitmentLevel},
+        instruction::Instruction,
+        pubkey::Pubkey,
+    },
+    std::{fs, path::Path, time::Duration},
+};
+
+/// The sections of a document that are read with the variables they declare, so they are
+/// not substituted up front
+const UNSUBSTITUTED: &[&str] = &["vars", "accounts", "programs", "fixtures"];
+
+/// A parsed transaction file
+pub struct TxFile {
+    /// The documents that hold instructions, in file order. Documents that only declare
+    /// variables are left out.
+    pub documents: Vec<Document>,
+    /// The number of documents in the file
+    pub document_count: usize,
+    /// The programs and accounts `soltx test` sets up, from every document
+    pub fixtures: Fixtures,
+}
+
+impl TxFile {
+    /// Parses every document of `source`. Variables declared by a document are visible to
+    /// the documents after it.
+    pub fn parse(
+        source: &str,
+        format: Format,
+        vars: &mut Variables,
+        idls: &mut Idls,
+    ) -> Result<TxFile, FileError> {
+        let nodes = format::load_documents(source, format)?;
+        let mut documents = vec![];
+        let mut fixtures = Fixtures::default();
+        for (index, node) in nodes.iter().enumerate() {
+            let document = parse_document(node, vars, idls, &mut fixtures).map_err(|error| {
+                FileError::Document {
+                    index,
+                    count: nodes.len(),
+                    error,
+                }
+            })?;
+            if let Some(mut document) = document {
+                document.index = index;
+                documents.push(document);
+            }
+        }
+        Ok(TxFile {
+            documents,
+            document_count: nodes.len(),
+            fixtures,
+        })
+    }
+
+    /// Reads and parses the file at `path`, in the format of its extension, without any
+    /// variable overrides. Anchor IDLs are read from files only, so loading never touches
+    /// the network.
+    pub fn load(path: impl AsRef<Path>) -> Result<TxFile, FileError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(FileError::Io)?;
+        let mut idls = Idls::offline(path.parent().unwrap_or_else(|| Path::new("")));
+        let format = Format::from_path(path);
+        Self::parse(&source, format, &mut Variables::default(), &mut idls)
+    }
+}
+
+/// The contents of a single yaml document, which is sent as one transaction
+pub struct Document {
+    /// 0-based position of the document in its file
+    pub index: usize,
+    pub instructions: Vec<Instruction>,
+    /// Signer sources given by accounts' `signer` fields
+    pub signer_sources: Vec<(Pubkey, String)>,
+    pub options: SendOptions,
+    pub nonce: Option<NonceSpec>,
+    /// Signer source of the account that pays the fees, defaults to the default signer
+    pub fee_payer: Option<String>,
+    pub compute_budget: ComputeBudget,
+    /// Checked once the transaction executed
+    pub expectations: Vec<Expectation>,
+}
+
+/// The `nonce` section of a document
+#[derive(Clone, Debug)]
+pub struct NonceSpec {
+    pub account: Pubkey,
+    /// Signer source of the nonce authority, defaults to the default signer
+    pub authority: Option<String>,
+    /// Creates and funds the nonce account with this many lamports instead of using it.
+    /// The nonce account's own `signer` must be given for this.
+    pub create: Option<u64>,
+    /// Withdraws lamports from the nonce account to `(to, lamports)`
+    pub withdraw: Option<(Pubkey, u64)>,
+}
+
+/// How a transaction is sent and confirmed. Unset options fall back to the command line
+/// and then to the defaults.
+#[derive(Clone, Debug, Default)]
+pub struct SendOptions {
+    pub commitment: Option<CommitmentLevel>,
+    pub skip_preflight: Option<bool>,
+    /// Defaults to `commitment`
+    pub preflight_commitment: Option<CommitmentLevel>,
+    /// Return right after the transaction was sent instead of waiting for confirmation
+    pub no_wait: Option<bool>,
+    /// How often a dropped transaction is sent, defaults to once
+    pub max_attempts: Option<u32>,
+    /// Milliseconds to wait before the first retry, doubled for every retry after it
+    pub retry_backoff: Option<u64>,
+}
+
+impl SendOptions {
+    /// Fills the unset options from `defaults`
+    pub fn or(&self, defaults: &SendOptions) -> SendOptions {
+        SendOptions {
+            commitment: self.commitment.or(defaults.commitment),
+            skip_preflight: self.skip_preflight.or(defaults.skip_preflight),
+            preflight_commitment: self.preflight_commitment.or(defaults.preflight_commitment),
+            no_wait: self.no_wait.or(defaults.no_wait),
+            max_attempts: self.max_attempts.or(defaults.max_attempts),
+            retry_backoff: self.retry_backoff.or(defaults.retry_backoff),
+        }
+    }
+
+    pub fn commitment_config(&self) -> CommitmentConfig {
+        CommitmentConfig {
+            commitment: self.commitment.unwrap_or(CommitmentLevel::Confirmed),
+        }
+    }
+
+    pub fn preflight_commitment(&self) -> CommitmentLevel {
+        self.preflight_commitment
+            .unwrap_or_else(|| self.commitment_config().commitment)
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(1).max(1)
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.retry_backoff.unwrap_or(1000))
+    }
+}
+
+/// The compute budget a transaction requests. Unset settings fall back to the command line,
+/// and without either the transaction has no ComputeBudget instructions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputeBudget {
+    pub unit_limit: Option<ComputeUnitLimit>,
+    /// Priority fee in micro-lamports per compute unit
+    pub unit_price: Option<u64>,
+}
+
+impl ComputeBudget {
+    /// Fills the unset settings from `defaults`
+    pub fn or(&self, defaults: &ComputeBudget) -> ComputeBudget {
+        ComputeBudget {
+            unit_limit: self.unit_limit.or(defaults.unit_limit),
+            unit_price: self.unit_price.or(defaults.unit_price),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComputeUnitLimit {
+    Units(u32),
+    /// The units consumed in a simulation, plus a margin
+    Auto,
+}
+
+/// Parses `auto` or a number of compute units
+pub fn parse_compute_unit_limit(limit: &str) -> Option<ComputeUnitLimit> {
+    match limit {
+        "auto" => Some(ComputeUnitLimit::Auto),
+        _ => limit.parse().ok().map(ComputeUnitLimit::Units),
+    }
+}
+
+/// Parses `processed`, `confirmed` or `finalized`
+pub fn parse_commitment(commitment: &str) -> Option<CommitmentLevel> {
+    match commitment {
+        "processed" => Some(CommitmentLevel::Processed),
+        "confirmed" => Some(CommitmentLevel::Confirmed),
+        "finalized" => Some(CommitmentLevel::Finalized),
+        _ => None,
+    }
+}
+
+/// Parses a yaml document, which is either a sequence of instructions or a mapping with
+/// an `instructions` sequence and optional `vars`/`accounts` sections, see [`DocumentSpec`].
+/// Returns `None` for an empty document, like the one yaml reads after a trailing `---`,
+/// and for a document that only declares variables for the documents after it.
+/// The `programs` and `fixtures` sections of a document are added to `fixtures`.
+pub fn parse_document(
+    node: &Node,
+    vars: &mut Variables,
+    idls: &mut Idls,
+    fixtures: &mut Fixtures,
+) -> Result<Option<Document>, ParseError> {
+    if node.is_null() {
+        return Ok(None);
+    }
+    let mut document = Document {
+        index: 0,
+        instructions: vec![],
+        signer_sources: vec![],
+        options: SendOptions::default(),
+        nonce: None,
+        fee_payer: None,
+        compute_budget: ComputeBudget::default(),
+        expectations: vec![],
+    };
+    let (instructions, path) = match &node.value {
+        Value::Mapping(entries) => {
+            for section in &["vars", "accounts"] {
+                if let Some(declarations) = node.get(section) {
+                    vars.declare(declarations, section)?;
+                }
+            }
+            fixtures.parse(node, vars)?;
+            let entries = entries
+                .iter()
+                .map(|(key, value)| match key.as_str() {
+                    Some(name) if !UNSUBSTITUTED.contains(&name) => {
+                        Ok((key.clone(), vars.substitute(value, name)?))
+                    }
+                    _ => Ok((key.clone(), value.clone())),
+                })
+                .collect::<Result<_, ParseError>>()?;
+            let spec = from_node::<DocumentSpec>(
+                &Node {
+                    value: Value::Mapping(entries),
+                    position: node.position,
+                },
+                "",
+            )?;
+            let instructions = match spec.instructions {
+                Some(instructions) => instructions,
+                None => return Ok(None),
+            };
+            document.options = SendOptions {
+                commitment: spec.commitment,
+                skip_preflight: spec.skip_preflight,
+                preflight_commitment: spec.preflight_commitment,
+                no_wait: spec.no_wait,
+                max_attempts: spec.max_attempts,
+                retry_backoff: spec.retry_backoff,
+            };
+            document.compute_budget = ComputeBudget {
+                unit_limit: spec.compute_unit_limit,
+                unit_price: spec.compute_unit_price,
+            };
+            if let Some(nonce) = &spec.nonce {
+                document.nonce = Some(parse_nonce(nonce, "nonce", &mut document.signer_sources)?);
+            }
+            document.fee_payer = spec.fee_payer;
+            if let Some(expect) = &spec.expect {
+                document.expectations = expect::parse_expectations(expect, "expect")?;
+            }
+            (instructions, "instructions")
+        }
+        _ => {
+            let instructions = vars.substitute(node, "")?;
+            let instructions = instructions.as_sequence().ok_or_else(|| {
+                ParseError::invalid_type(&instructions, "", "a sequence of instructions")
+            })?;
+            (instructions.to_vec(), "")
+        }
+    };
+    for (index, instruction) in instructions.iter().enumerate() {
+        let instruction = yaml_to_instruction(
+            instruction,
+            &format!("{}[{}]", path, index),
+            &mut document.signer_sources,
+            idls,
+        )?;
+        document.instructions.push(instruction);
+    }
+    Ok(Some(document))
+}
+
+fn parse_nonce(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+) -> Result<NonceSpec, ParseError> {
+    check_fields(
+        yaml,
+        path,
+        &["account", "signer", "authority", "create", "withdraw"],
+    )?;
+    let account = parse_pubkey(field(yaml, path, "account")?, &format!("{}.account", path))?;
+    let string = |name: &str| -> Result<Option<String>, ParseError> {
+        yaml.get(name)
+            .map(|node| {
+                node.as_str().map(str::to_string).ok_or_else(|| {
+                    ParseError::invalid_type(node, &format!("{}.{}", path, name), "a string")
+                })
+            })
+            .transpose()
+    };
+    if let Some(source) = string("signer")? {
+        signer_sources.push((account, source));
+    }
+    let create = yaml
+        .get("create")
+        .map(|create| {
+            let path = format!("{}.create", path);
+            check_fields(create, &path, &["lamports"])?;
+            number::<u64>(
+                field(create, &path, "lamports")?,
+                &format!("{}.lamports", path),
+            )
+        })
+        .transpose()?;
+    let withdraw = yaml
+        .get("withdraw")
+        .map(|withdraw| -> Result<_, ParseError> {
+            let path = format!("{}.withdraw", path);
+            check_fields(withdraw, &path, &["to", "lamports"])?;
+            let to = parse_pubkey(field(withdraw, &path, "to")?, &format!("{}.to", path))?;
+            let lamports = number::<u64>(
+                field(withdraw, &path, "lamports")?,
+                &format!("{}.lamports", path),
+            )?;
+            Ok((to, lamports))
+        })
+        .transpose()?;
+    Ok(NonceSpec {
+        account,
+        authority: string("authority")?,
+        create,
+        withdraw,
+    })
+}
+
+fn yaml_to_instruction(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+    idls: &mut Idls,
+) -> Result<Instruction, ParseError> {
+    if idl::is_anchor_instruction(yaml) {
+        return idl::parse_anchor_instruction(yaml, path, signer_sources, idls);
+    }
+    if programs::is_shorthand(yaml) {
+        return programs::parse_shorthand(yaml, path, signer_sources);
+    }
+    let instruction = from_node::<InstructionSpec>(yaml, path)?;
+    for account in &instruction.accounts {
+        if let Some(source) = &account.signer {
+            signer_sources.push((account.key.0, source.clone()));
+        }
+    }
+    Ok(instruction.to_instruction())
+}
+
+/// Records the optional `signer` source of the account `pubkey` described by `yaml`
+pub fn parse_signer(
+    yaml: &Node,
+    path: &str,
+    pubkey: Pubkey,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+) -> Result<(), ParseError> {
+    if let Some(source) = yaml.get("signer") {
+        let source = source.as_str().ok_or_else(|| {
+            ParseError::invalid_type(source, &format!("{}.signer", path), "a string")
+        })?;
+        signer_sources.push((pubkey, source.to_string()));
+    }
+    Ok(())
+}
+
+/// Parses an account given as a pubkey or as `{key, signer}`, recording the signer
+/// source of the latter
+pub fn parse_account_key(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+) -> Result<Pubkey, ParseError> {
+    if yaml.get("key").is_none() {
+        return parse_pubkey(yaml, path);
+    }
+    check_fields(yaml, path, &["key", "signer"])?;
+    let pubkey = parse_pubkey(field(yaml, path, "key")?, &format!("{}.key", path))?;
+    parse_signer(yaml, path, pubkey, signer_sources)?;
+    Ok(pubkey)
+}
+
+/// Parses a base58 pubkey or an address derived with `pda:` or `ata:`, see
+/// [`AddressSpec`]
+pub fn parse_pubkey(yaml: &Node, path: &str) -> Result<Pubkey, ParseError> {
+    from_node::<AddressSpec>(yaml, path).map(|AddressSpec(pubkey)| pubkey)
+}
+
+/// Returns the value of `name` in the mapping `yaml`
+pub fn field<'a>(yaml: &'a Node, path: &str, name: &'static str) -> Result<&'a Node, ParseError> {
+    if yaml.as_mapping().is_none() {
+        return Err(ParseError::invalid_type(yaml, path, "a mapping"));
+    }
+    yaml.get(name)
+        .ok_or_else(|| ParseError::new(yaml, path, ParseErrorKind::MissingField(name)))
+}
+
+/// Fails on the first key of the mapping `yaml` that is not one of `expected`, so that a
+/// misspelled optional field is not silently ignored
+pub fn check_fields<S: AsRef<str>>(
+    yaml: &Node,
+    path: &str,
+    expected: &[S],
+) -> Result<(), ParseError> {
+    for (key, _) in yaml.as_mapping().unwrap_or_default() {
+        let name = key.as_str().unwrap_or_default();
+        if !expected.iter().any(|field| field.as_ref() == name) {
+            let field_path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            };
+            return Err(ParseError::new(
+                key,
+                &field_path,
+                ParseErrorKind::UnknownField {
+                    field: name.to_string(),
+                    expected: expected
+                        .iter()
+                        .map(AsRef::as_ref)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                },
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{data::parse_field, yaml::load_from_str},
+        solana_sdk::pubkey::MAX_SEEDS,
+        spl_associated_token_account::get_associated_token_address,
+    };
+
+    fn node(source: &str) -> Node {
+        load_from_str(source).unwrap().remove(0)
+    }
+
+    #[test]
+    fn empty_documents_are_skipped() {
+        let source = "- programId: tLSGV7BXFM2LfS3jwFyk5bAqDMisNQE4FUWPMxNnXJZ\n  \
+                      accounts: []\n  data: 1\n---\n";
+        let tx_file = TxFile::parse(
+            source,
+            Format::Yaml,
+            &mut Variables::default(),
+            &mut Idls::offline(""),
+        )
+        .unwrap();
+        assert_eq!(tx_file.document_count, 2);
+        assert_eq!(tx_file.documents.len(), 1);
+        assert_eq!(tx_file.documents[0].instructions[0].data, vec![1]);
+    }
+
+    #[test]
+    fn pda_and_bump() {
+        let program = Pubkey::new_unique();
+        let pda = format!(
+            "pda: {{program: {}, seeds: [{{utf8: vault}}, {{u64: 7}}]}}",
+            program
+        );
+        let (address, bump) =
+            Pubkey::find_program_address(&[b"vault", &7u64.to_le_bytes()], &program);
+        assert_eq!(parse_pubkey(&node(&pda), "key").unwrap(), address);
+        assert_eq!(
+            parse_field(&node(&format!("bump: {{{}}}", pda)), "data").unwrap(),
+            vec![bump]
+        );
+    }
+
+    #[test]
+    fn ata() {
+        let (wallet, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ata = format!("ata: {{wallet: {}, mint: {}}}", wallet, mint);
+        assert_eq!(
+            parse_pubkey(&node(&ata), "key").unwrap(),
+            get_associated_token_address(&wallet, &mint)
+        );
+    }
+
+    #[test]
+    fn invalid_addresses() {
+        let program = Pubkey::new_unique();
+        let err = parse_pubkey(&node("nope"), "key").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid pubkey `nope` at key (line 1, column 1)"
+        );
+
+        let seeds = vec!["{u8: 1}"; MAX_SEEDS].join(", ");
+        let pda = format!("pda: {{program: {}, seeds: [{}]}}", program, seeds);
+        let err = parse_pubkey(&node(&pda), "key").unwrap_err();
+        assert_eq!(err.path, "key");
+        assert!(err.to_string().contains("at most 15 are allowed"));
+
+        let pda = format!(
+            "pda: {{program: {}, seeds: [{{hex: '{}'}}]}}",
+            program,
+            "00".repeat(33)
+        );
+        let err = parse_pubkey(&node(&pda), "key").unwrap_err();
+        assert_eq!(err.path, "key.pda.seeds[0]");
+
+        let err =
+            parse_pubkey(&node(&format!("pdq: {{program: {}}}", program)), "key").unwrap_err();
+        assert!(matches!(err.kind, ParseErrorKind::UnknownTag { tag, .. } if tag == "pdq"));
+    }
+}
@@ -0,0 +1,421 @@
+use {
+    crate::{
+        data::{number, tagged},
+        error::{ParseError, ParseErrorKind},
+        parse::{check_fields, field, parse_account_key},
+        yaml::Node,
+    },
+    solana_sdk::{
+        instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, system_instruction,
+    },
+    spl_memo::build_memo,
+    spl_token::instruction as token_instruction,
+    std::str::FromStr,
+};
+
+const SHORTHANDS: &str = "transfer, createAccount, assign, allocate, memo, \
+                          spl-token.initializeMint, spl-token.initializeAccount, \
+                          spl-token.transfer, spl-token.transferChecked, spl-token.mintTo, \
+                          spl-token.mintToChecked, spl-token.burn, spl-token.burnChecked, \
+                          spl-token.approve, spl-token.revoke, spl-token.closeAccount, \
+                          spl-token.freezeAccount or spl-token.thawAccount";
+
+/// The programs whose shorthands take a `program.` prefix
+const SHORTHAND_PROGRAMS: &str = "spl-token";
+
+/// Whether `yaml` is a built-in instruction kind like `{transfer: {...}}` rather than a
+/// raw `programId`/`accounts`/`data` instruction
+pub fn is_shorthand(yaml: &Node) -> bool {
+    matches!(yaml.as_mapping(), Some([_])) && yaml.get("programId").is_none()
+}
+
+/// Expands a built-in instruction kind of the system, spl-token or memo program with the
+/// program's own instruction builder.
+///
+/// Any pubkey argument may be written as `{key, signer}` to give the source of its
+/// signer, like an account of a raw instruction.
+pub fn parse_shorthand(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+) -> Result<Instruction, ParseError> {
+    let (kind, args) = tagged(yaml, path, SHORTHANDS)?;
+    if let Some(dot) = kind.find('.') {
+        let program = &kind[..dot];
+        if program != "spl-token" {
+            return Err(ParseError::new(
+                yaml,
+                path,
+                ParseErrorKind::UnknownProgram {
+                    program: program.to_string(),
+                    expected: SHORTHAND_PROGRAMS,
+                },
+            ));
+        }
+    }
+    let path = &format!("{}.{}", path, kind);
+    if kind == "memo" {
+        return parse_memo(args, path, signer_sources);
+    }
+    let mut args = Args {
+        yaml: args,
+        path,
+        signer_sources,
+        used: vec![],
+    };
+    let token_program = &spl_token::id();
+    let instruction = match kind {
+        "transfer" => system_instruction::transfer(
+            &args.pubkey("from")?,
+            &args.pubkey("to")?,
+            args.number("lamports")?,
+        ),
+        "createAccount" => system_instruction::create_account(
+            &args.pubkey("from")?,
+            &args.pubkey("to")?,
+            args.number("lamports")?,
+            args.number("space")?,
+            &args.pubkey("owner")?,
+        ),
+        "assign" => system_instruction::assign(&args.pubkey("account")?, &args.pubkey("owner")?),
+        "allocate" => system_instruction::allocate(&args.pubkey("account")?, args.number("space")?),
+        "spl-token.initializeMint" => token(token_instruction::initialize_mint(
+            token_program,
+            &args.pubkey("mint")?,
+            &args.pubkey("mintAuthority")?,
+            args.optional_pubkey("freezeAuthority")?.as_ref(),
+            args.number("decimals")?,
+        )),
+        "spl-token.initializeAccount" => token(token_instruction::initialize_account(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("owner")?,
+        )),
+        "spl-token.transfer" => token(token_instruction::transfer(
+            token_program,
+            &args.pubkey("source")?,
+            &args.pubkey("destination")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+        )),
+        "spl-token.transferChecked" => token(token_instruction::transfer_checked(
+            token_program,
+            &args.pubkey("source")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("destination")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+            args.number("decimals")?,
+        )),
+        "spl-token.mintTo" => token(token_instruction::mint_to(
+            token_program,
+            &args.pubkey("mint")?,
+            &args.pubkey("account")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+        )),
+        "spl-token.mintToChecked" => token(token_instruction::mint_to_checked(
+            token_program,
+            &args.pubkey("mint")?,
+            &args.pubkey("account")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+            args.number("decimals")?,
+        )),
+        "spl-token.burn" => token(token_instruction::burn(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+        )),
+        "spl-token.burnChecked" => token(token_instruction::burn_checked(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("authority")?,
+            &[],
+            args.number("amount")?,
+            args.number("decimals")?,
+        )),
+        "spl-token.approve" => token(token_instruction::approve(
+            token_program,
+            &args.pubkey("source")?,
+            &args.pubkey("delegate")?,
+            &args.pubkey("owner")?,
+            &[],
+            args.number("amount")?,
+        )),
+        "spl-token.revoke" => token(token_instruction::revoke(
+            token_program,
+            &args.pubkey("source")?,
+            &args.pubkey("owner")?,
+            &[],
+        )),
+        "spl-token.closeAccount" => token(token_instruction::close_account(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("destination")?,
+            &args.pubkey("owner")?,
+            &[],
+        )),
+        "spl-token.freezeAccount" => token(token_instruction::freeze_account(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("authority")?,
+            &[],
+        )),
+        "spl-token.thawAccount" => token(token_instruction::thaw_account(
+            token_program,
+            &args.pubkey("account")?,
+            &args.pubkey("mint")?,
+            &args.pubkey("authority")?,
+            &[],
+        )),
+        _ => {
+            return Err(ParseError::new(
+                yaml,
+                path,
+                ParseErrorKind::UnknownTag {
+                    tag: kind.to_string(),
+                    expected: SHORTHANDS.to_string(),
+                },
+            ))
+        }
+    };
+    check_fields(args.yaml, path, &args.used)?;
+    Ok(instruction)
+}
+
+/// `memo: text` or `memo: {text, signers}`
+fn parse_memo(
+    yaml: &Node,
+    path: &str,
+    signer_sources: &mut Vec<(Pubkey, String)>,
+) -> Result<Instruction, ParseError> {
+    if let Some(text) = yaml.as_str() {
+        return Ok(build_memo(text.as_bytes(), &[]));
+    }
+    if yaml.as_mapping().is_none() {
+        return Err(ParseError::invalid_type(
+            yaml,
+            path,
+            "a string or a mapping",
+        ));
+    }
+    check_fields(yaml, path, &["text", "signers"])?;
+    let text = field(yaml, path, "text")?;
+    let text = text
+        .as_str()
+        .ok_or_else(|| ParseError::invalid_type(text, &format!("{}.text", path), "a string"))?;
+    let mut args = Args {
+        yaml,
+        path,
+        signer_sources,
+        used: vec![],
+    };
+    let signers = match yaml.get("signers") {
+        None => vec![],
+        Some(signers) => signers
+            .as_sequence()
+            .ok_or_else(|| {
+                ParseError::invalid_type(signers, &format!("{}.signers", path), "a sequence")
+            })?
+            .iter()
+            .enumerate()
+            .map(|(index, signer)| args.parse_pubkey(signer, &format!("signers[{}]", index)))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok(build_memo(
+        text.as_bytes(),
+        &signers.iter().collect::<Vec<_>>(),
+    ))
+}
+
+/// The spl-token builders only fail for a foreign token program id or too many multisig
+/// signers, and soltx passes neither
+fn token(instruction: Result<Instruction, ProgramError>) -> Instruction {
+    // unwrap OK because of the above
+    instruction.unwrap()
+}
+
+/// The arguments of a shorthand instruction
+struct Args<'a> {
+    yaml: &'a Node,
+    path: &'a str,
+    signer_sources: &'a mut Vec<(Pubkey, String)>,
+    /// The argument names read so far, the only ones the mapping may hold
+    used: Vec<&'static str>,
+}
+
+impl Args<'_> {
+    fn pubkey(&mut self, name: &'static str) -> Result<Pubkey, ParseError> {
+        self.used.push(name);
+        let value = field(self.yaml, self.path, name)?;
+        self.parse_pubkey(value, name)
+    }
+
+    fn optional_pubkey(&mut self, name: &'static str) -> Result<Option<Pubkey>, ParseError> {
+        self.used.push(name);
+        match self.yaml.get(name) {
+            Some(value) if !value.is_null() => self.parse_pubkey(value, name).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_pubkey(&mut self, value: &Node, name: &str) -> Result<Pubkey, ParseError> {
+        parse_account_key(
+            value,
+            &format!("{}.{}", self.path, name),
+            self.signer_sources,
+        )
+    }
+
+    fn number<T: FromStr>(&mut self, name: &'static str) -> Result<T, ParseError> {
+        self.used.push(name);
+        number(
+            field(self.yaml, self.path, name)?,
+            &format!("{}.{}", self.path, name),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::yaml::load_from_str};
+
+    fn expand(source: &str) -> Result<Instruction, ParseError> {
+        let yaml = load_from_str(source).unwrap().remove(0);
+        parse_shorthand(&yaml, "instructions[0]", &mut vec![])
+    }
+
+    #[test]
+    fn system_shorthands() {
+        let (from, to, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(
+            expand(&format!(
+                "transfer: {{from: {}, to: {}, lamports: 5}}",
+                from, to
+            ))
+            .unwrap(),
+            system_instruction::transfer(&from, &to, 5)
+        );
+        assert_eq!(
+            expand(&format!(
+                "createAccount: {{from: {}, to: {}, lamports: 5, space: 165, owner: {}}}",
+                from, to, owner
+            ))
+            .unwrap(),
+            system_instruction::create_account(&from, &to, 5, 165, &owner)
+        );
+        assert_eq!(
+            expand(&format!("allocate: {{account: {}, space: 8}}", to)).unwrap(),
+            system_instruction::allocate(&to, 8)
+        );
+    }
+
+    #[test]
+    fn token_shorthands() {
+        let (mint, account, authority) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let token_program = &spl_token::id();
+        assert_eq!(
+            expand(&format!(
+                "spl-token.initializeMint: {{mint: {}, mintAuthority: {}, decimals: 6}}",
+                mint, authority
+            ))
+            .unwrap(),
+            token_instruction::initialize_mint(token_program, &mint, &authority, None, 6).unwrap()
+        );
+        assert_eq!(
+            expand(&format!(
+                "spl-token.initializeMint: {{mint: {0}, mintAuthority: {1}, \
+                 freezeAuthority: {1}, decimals: 6}}",
+                mint, authority
+            ))
+            .unwrap(),
+            token_instruction::initialize_mint(
+                token_program,
+                &mint,
+                &authority,
+                Some(&authority),
+                6
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            expand(&format!(
+                "spl-token.transferChecked: {{source: {0}, mint: {1}, destination: {0}, \
+                 authority: {2}, amount: 100, decimals: 6}}",
+                account, mint, authority
+            ))
+            .unwrap(),
+            token_instruction::transfer_checked(
+                token_program,
+                &account,
+                &mint,
+                &account,
+                &authority,
+                &[],
+                100,
+                6
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            expand(&format!(
+                "spl-token.closeAccount: {{account: {}, destination: {1}, owner: {1}}}",
+                account, authority
+            ))
+            .unwrap(),
+            token_instruction::close_account(token_program, &account, &authority, &authority, &[])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn memo_shorthands() {
+        assert_eq!(expand("memo: hello").unwrap(), build_memo(b"hello", &[]));
+        let signer = Pubkey::new_unique();
+        let yaml = load_from_str(&format!(
+            "memo: {{text: hello, signers: [{{key: {}, signer: usb://ledger}}]}}",
+            signer
+        ))
+        .unwrap()
+        .remove(0);
+        let mut signer_sources = vec![];
+        assert_eq!(
+            parse_shorthand(&yaml, "instructions[0]", &mut signer_sources).unwrap(),
+            build_memo(b"hello", &[&signer])
+        );
+        assert_eq!(signer_sources, vec![(signer, "usb://ledger".to_string())]);
+    }
+
+    #[test]
+    fn invalid_shorthands() {
+        let to = Pubkey::new_unique();
+        let message = |source: &str| expand(source).unwrap_err().to_string();
+        assert!(message("stake.delegate: {}").contains("stake"));
+        assert!(message("spl-token.mint: {}").contains("spl-token.mint"));
+        assert!(message(&format!("transfer: {{from: {0}, to: {0}}}", to)).contains("lamports"));
+        assert!(message(&format!(
+            "transfer: {{from: {0}, to: {0}, lamports: 1, memo: hi}}",
+            to
+        ))
+        .contains("memo"));
+    }
+}
@@ -0,0 +1,87 @@
+//! The structured output of `--output json`, one object per transaction
+
+use {
+    crate::sender::Simulation,
+    serde::Serialize,
+    solana_sdk::{clock::Slot, transaction::Transaction},
+    solana_transaction_status::EncodedConfirmedTransaction,
+};
+
+pub const OUTPUTS: &[&str] = &["text", "json"];
+
+/// What became of a transaction
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Confirmed at the requested commitment
+    Confirmed,
+    /// Sent with `--no-wait`, its outcome is unknown
+    Sent,
+    /// Rejected by the preflight simulation, or confirmed with an error
+    Failed,
+    Simulated,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub signature: String,
+    /// The slot the transaction landed in, if it was confirmed
+    pub slot: Option<Slot>,
+    /// The blockhash the transaction was signed with, a nonce's for nonced transactions
+    pub blockhash: String,
+    pub fee: Option<u64>,
+    pub status: Status,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub signers: Vec<String>,
+    /// How often the transaction was sent
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units_consumed: Option<u64>,
+}
+
+impl Report {
+    pub fn new(transaction: &Transaction, status: Status) -> Self {
+        let message = &transaction.message;
+        let signer_count = message.header.num_required_signatures as usize;
+        Self {
+            signature: transaction.signatures[0].to_string(),
+            slot: None,
+            blockhash: message.recent_blockhash.to_string(),
+            fee: None,
+            status,
+            error: None,
+            logs: vec![],
+            signers: message.account_keys[..signer_count]
+                .iter()
+                .map(|pubkey| pubkey.to_string())
+                .collect(),
+            attempts: 0,
+            units_consumed: None,
+        }
+    }
+
+    pub fn simulated(transaction: &Transaction, simulation: &Simulation) -> Self {
+        Self {
+            error: simulation.err.as_ref().map(|err| err.to_string()),
+            logs: simulation.logs.clone(),
+            units_consumed: Some(simulation.units_consumed),
+            ..Self::new(transaction, Status::Simulated)
+        }
+    }
+
+    /// Fills in the slot, fee, error and logs of the transaction as the cluster
+    /// confirmed it
+    pub fn set_confirmed(&mut self, confirmed: &EncodedConfirmedTransaction) {
+        self.slot = Some(confirmed.slot);
+        if let Some(meta) = &confirmed.transaction.meta {
+            self.fee = Some(meta.fee);
+            self.logs = meta.log_messages.clone().unwrap_or_default();
+            if let Some(err) = &meta.err {
+                self.status = Status::Failed;
+                self.error = Some(err.to_string());
+            }
+        }
+    }
+}
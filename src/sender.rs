@@ -0,0 +1,662 @@
+//! Sends, simulates and fetches transactions over RPC, and the [`Sender`] trait that
+//! abstracts over RPC and the in-process bank of `soltx test`
+
+use {
+    crate::{
+        compute_budget::BUILTIN_INSTRUCTION_UNITS,
+        expect::{send_expecting, Execution},
+        parse::SendOptions,
+        report::{Report, Status},
+        Document,
+    },
+    anyhow::{anyhow, bail, Context, Result},
+    solana_client::{
+        blockhash_query::BlockhashQuery,
+        client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+        pubsub_client::PubsubClient,
+        rpc_client::RpcClient,
+        rpc_config::{
+            RpcConfirmedTransactionConfig, RpcSendTransactionConfig, RpcSignatureSubscribeConfig,
+            RpcSimulateTransactionConfig,
+        },
+        rpc_request::{RpcError, RpcResponseErrorData},
+        rpc_response::{
+            ProcessedSignatureResult, RpcSignatureResult, RpcSimulateTransactionResult,
+        },
+    },
+    solana_sdk::{
+        account::Account,
+        commitment_config::CommitmentConfig,
+        hash::Hash,
+        message::Message,
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::{uses_durable_nonce, Transaction, TransactionError},
+    },
+    solana_transaction_status::{EncodedConfirmedTransaction, UiTransactionEncoding},
+    std::{sync::mpsc::RecvTimeoutError, thread::sleep, time::Duration},
+};
+
+/// The outcome of a simulated transaction
+#[derive(Debug)]
+pub struct Simulation {
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: u64,
+}
+
+/// Where transactions go: a cluster over RPC, or the in-process bank of `soltx test`
+pub trait Sender {
+    /// The blockhash to sign with, which for a nonced transaction is the nonce stored in
+    /// `nonce_account`
+    fn get_blockhash(
+        &mut self,
+        nonce_account: Option<&Pubkey>,
+        send_options: &SendOptions,
+    ) -> Result<Hash>;
+
+    /// Sends `transaction` under the retry policy of `send_options`, like
+    /// [`send_with_retries`]
+    fn send(
+        &mut self,
+        transaction: &mut Transaction,
+        send_options: &SendOptions,
+        resign: &mut dyn FnMut(Hash) -> Result<Transaction>,
+        report: &mut dyn FnMut(&Attempt),
+    ) -> Result<Signature>;
+
+    fn simulate(
+        &mut self,
+        transaction: &Transaction,
+        send_options: &SendOptions,
+    ) -> Result<Simulation>;
+
+    fn get_account(
+        &mut self,
+        address: &Pubkey,
+        send_options: &SendOptions,
+    ) -> Result<Option<Account>>;
+
+    /// Whether [`Sender::execution`] has the logs of a transaction, which `logsContain`
+    /// expectations need
+    fn returns_logs(&self) -> bool {
+        true
+    }
+
+    /// How `transaction` executed, given the `result` of sending it, or `None` if it never
+    /// executed because it could not be sent
+    fn execution(
+        &mut self,
+        transaction: &Transaction,
+        result: &Result<Signature>,
+        send_options: &SendOptions,
+    ) -> Result<Option<Execution>>;
+}
+
+/// Sends to a cluster over RPC
+pub struct RpcSender<'a> {
+    pub rpc_client: &'a RpcClient,
+    /// Where confirmations are subscribed to
+    pub websocket_url: &'a str,
+    /// Sign with this blockhash, from `--blockhash`, instead of the cluster's
+    pub blockhash: Option<Hash>,
+    /// Use `blockhash` without checking with the cluster that it is still valid
+    pub sign_only: bool,
+}
+
+impl Sender for RpcSender<'_> {
+    fn get_blockhash(
+        &mut self,
+        nonce_account: Option<&Pubkey>,
+        send_options: &SendOptions,
+    ) -> Result<Hash> {
+        let (blockhash, _fee_calculator) = BlockhashQuery::new(
+            self.blockhash,
+            self.sign_only && self.blockhash.is_some(),
+            nonce_account.copied(),
+        )
+        .get_blockhash_and_fee_calculator(self.rpc_client, send_options.commitment_config())
+        .map_err(|err| anyhow!("failed to get a blockhash: {}", err))?;
+        Ok(blockhash)
+    }
+
+    fn send(
+        &mut self,
+        transaction: &mut Transaction,
+        send_options: &SendOptions,
+        resign: &mut dyn FnMut(Hash) -> Result<Transaction>,
+        report: &mut dyn FnMut(&Attempt),
+    ) -> Result<Signature> {
+        send_with_retries(
+            transaction,
+            self.rpc_client,
+            self.websocket_url,
+            send_options,
+            resign,
+            report,
+        )
+    }
+
+    fn simulate(
+        &mut self,
+        transaction: &Transaction,
+        send_options: &SendOptions,
+    ) -> Result<Simulation> {
+        simulate_transaction(transaction, self.rpc_client, send_options)
+    }
+
+    fn get_account(
+        &mut self,
+        address: &Pubkey,
+        send_options: &SendOptions,
+    ) -> Result<Option<Account>> {
+        Ok(self
+            .rpc_client
+            .get_account_with_commitment(address, send_options.commitment_config())?
+            .value)
+    }
+
+    fn execution(
+        &mut self,
+        transaction: &Transaction,
+        result: &Result<Signature>,
+        send_options: &SendOptions,
+    ) -> Result<Option<Execution>> {
+        let (err, preflight_logs) = match result {
+            Ok(_) if send_options.no_wait.unwrap_or_default() => {
+                bail!("the outcome of a transaction sent with noWait is unknown")
+            }
+            Ok(_) => (None, None),
+            Err(err) => match preflight_failure(err) {
+                Some(simulation) => (simulation.err.clone(), simulation.logs.clone()),
+                None => match transaction_error(err) {
+                    Some(err) => (Some(err), None),
+                    None => return Ok(None),
+                },
+            },
+        };
+        // a transaction rejected by preflight never landed, one that failed on chain did
+        let logs = match preflight_logs {
+            Some(logs) => logs,
+            None => fetch_confirmed_transaction(
+                self.rpc_client,
+                &transaction.signatures[0],
+                send_options,
+            )?
+            .transaction
+            .meta
+            .and_then(|meta| meta.log_messages)
+            .unwrap_or_default(),
+        };
+        Ok(Some(Execution { err, logs }))
+    }
+}
+
+/// Sends `transaction` and, unless `no_wait` is set, waits for its confirmation.
+///
+/// The confirmation is notified by a signature subscription on `websocket_url`. Where the
+/// websocket cannot be reached the status is polled instead, with a spinner on stderr.
+pub fn send_transaction(
+    transaction: &Transaction,
+    rpc_client: &RpcClient,
+    websocket_url: &str,
+    send_options: &SendOptions,
+) -> Result<Signature> {
+    let config = RpcSendTransactionConfig {
+        skip_preflight: send_options.skip_preflight.unwrap_or_default(),
+        preflight_commitment: Some(send_options.preflight_commitment()),
+        encoding: None,
+    };
+    if send_options.no_wait.unwrap_or_default() {
+        return Ok(rpc_client.send_transaction_with_config(transaction, config)?);
+    }
+    let commitment = send_options.commitment_config();
+    // subscribing before sending cannot miss the notification
+    let subscription = PubsubClient::signature_subscribe(
+        websocket_url,
+        &transaction.signatures[0],
+        Some(RpcSignatureSubscribeConfig {
+            commitment: Some(commitment),
+            enable_received_notification: Some(false),
+        }),
+    );
+    let (_subscription, notifications) = match subscription {
+        Ok(subscription) => subscription,
+        Err(_) => {
+            return Ok(
+                rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+                    transaction,
+                    commitment,
+                    config,
+                )?,
+            )
+        }
+    };
+    // a nonced transaction never expires, it is given up like the spinner does, once a
+    // blockhash of the time it was sent expired
+    let recent_blockhash = if uses_durable_nonce(transaction).is_some() {
+        rpc_client
+            .get_recent_blockhash_with_commitment(CommitmentConfig::processed())?
+            .value
+            .0
+    } else {
+        transaction.message.recent_blockhash
+    };
+    let signature = rpc_client.send_transaction_with_config(transaction, config)?;
+    loop {
+        match notifications.recv_timeout(Duration::from_secs(1)) {
+            Ok(response) => match response.value {
+                RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult {
+                    err: Some(err),
+                }) => return Err(ClientError::from(err).into()),
+                RpcSignatureResult::ProcessedSignature(_) => return Ok(signature),
+                RpcSignatureResult::ReceivedSignature(_) => {}
+            },
+            // a dropped transaction is never notified, the spinner gives it up once its
+            // blockhash expired
+            Err(RecvTimeoutError::Timeout) => {
+                let expired = rpc_client
+                    .get_fee_calculator_for_blockhash_with_commitment(
+                        &recent_blockhash,
+                        CommitmentConfig::processed(),
+                    )?
+                    .value
+                    .is_none();
+                if expired {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    rpc_client.confirm_transaction_with_spinner(&signature, &recent_blockhash, commitment)?;
+    Ok(signature)
+}
+
+/// An attempt of [`send_with_retries`] to send a transaction, reported before it is sent
+pub struct Attempt<'a> {
+    /// 1-based
+    pub number: u32,
+    pub max_attempts: u32,
+    pub signature: Signature,
+    /// Why the previous attempt failed
+    pub previous_error: Option<&'a anyhow::Error>,
+    /// Whether the transaction was signed again with a fresh blockhash
+    pub resigned: bool,
+}
+
+/// Sends `transaction` like [`send_transaction`], and sends it again when it was dropped or
+/// could not be sent, up to `send_options.max_attempts` times with an exponential backoff.
+///
+/// Before each retry the status of the previous attempt is checked, so a transaction that
+/// landed after all is never sent twice. Once its blockhash is known to have expired the
+/// transaction is replaced by the one `resign` signs with a fresh blockhash, unless it
+/// uses a durable nonce. `transaction` is left as the last attempt sent it.
+pub fn send_with_retries(
+    transaction: &mut Transaction,
+    rpc_client: &RpcClient,
+    websocket_url: &str,
+    send_options: &SendOptions,
+    mut resign: impl FnMut(Hash) -> Result<Transaction>,
+    mut report: impl FnMut(&Attempt),
+) -> Result<Signature> {
+    let max_attempts = send_options.max_attempts();
+    let commitment = send_options.commitment_config();
+    let mut previous_error = None;
+    let mut resigned = false;
+    let mut number = 0;
+    loop {
+        number += 1;
+        report(&Attempt {
+            number,
+            max_attempts,
+            signature: transaction.signatures[0],
+            previous_error: previous_error.as_ref(),
+            resigned,
+        });
+        let err = match send_transaction(transaction, rpc_client, websocket_url, send_options) {
+            Ok(signature) => return Ok(signature),
+            Err(err) => err,
+        };
+        if number >= max_attempts || !is_retryable(&err) {
+            return Err(err);
+        }
+        // capped at 1024 times the backoff
+        sleep(send_options.retry_backoff() * 2u32.pow((number - 1).min(10)));
+
+        let signature = transaction.signatures[0];
+        let blockhash = transaction.message.recent_blockhash;
+        resigned = false;
+        // while the node cannot be reached the state stays unknown, so the same transaction
+        // is sent again, which can never land twice
+        match probe(transaction, rpc_client) {
+            Ok(Probe::Landed(status)) => {
+                status?;
+                if !send_options.no_wait.unwrap_or_default() {
+                    rpc_client
+                        .confirm_transaction_with_spinner(&signature, &blockhash, commitment)?;
+                }
+                return Ok(signature);
+            }
+            Ok(Probe::Expired) => {
+                if let Ok(response) = rpc_client.get_recent_blockhash_with_commitment(commitment) {
+                    let (blockhash, _fee_calculator, _last_valid_slot) = response.value;
+                    *transaction = resign(blockhash)
+                        .with_context(|| format!("failed to sign {} again", signature))?;
+                    resigned = true;
+                }
+            }
+            Ok(Probe::Pending) | Err(_) => {}
+        }
+        previous_error = Some(err);
+    }
+}
+
+/// What became of a transaction that failed to be sent or confirmed
+enum Probe {
+    /// It landed, with this outcome
+    Landed(Result<(), TransactionError>),
+    /// It can no longer land, its blockhash expired
+    Expired,
+    /// It may still land
+    Pending,
+}
+
+fn probe(transaction: &Transaction, rpc_client: &RpcClient) -> ClientResult<Probe> {
+    // a transaction cannot land once its blockhash expired, so checking the expiry first
+    // makes a missing status final
+    let expired = uses_durable_nonce(transaction).is_none()
+        && rpc_client
+            .get_fee_calculator_for_blockhash_with_commitment(
+                &transaction.message.recent_blockhash,
+                CommitmentConfig::processed(),
+            )?
+            .value
+            .is_none();
+    let status = rpc_client.get_signature_status_with_commitment(
+        &transaction.signatures[0],
+        CommitmentConfig::processed(),
+    )?;
+    Ok(match status {
+        Some(status) => Probe::Landed(status),
+        None if expired => Probe::Expired,
+        None => Probe::Pending,
+    })
+}
+
+/// Whether sending again may succeed where `err` failed: the transaction was dropped, its
+/// blockhash was not found, or the RPC node could not be reached. A transaction that was
+/// already processed is retried too, the status check before the retry then finds it.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<ClientError>().map(ClientError::kind) {
+        Some(ClientErrorKind::Io(_)) | Some(ClientErrorKind::Reqwest(_)) => true,
+        // confirm_transaction_with_spinner gives up on an expired transaction with this
+        Some(ClientErrorKind::RpcError(RpcError::ForUser(_))) => true,
+        Some(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data:
+                RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+                    err:
+                        Some(TransactionError::BlockhashNotFound)
+                        | Some(TransactionError::AlreadyProcessed),
+                    ..
+                }),
+            ..
+        })) => true,
+        _ => false,
+    }
+}
+
+pub fn simulate_transaction(
+    transaction: &Transaction,
+    rpc_client: &RpcClient,
+    send_options: &SendOptions,
+) -> Result<Simulation> {
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                commitment: Some(send_options.commitment_config()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+    let logs = result.logs.unwrap_or_default();
+    Ok(Simulation {
+        err: result.err,
+        units_consumed: units_consumed(&logs),
+        logs,
+    })
+}
+
+/// Simulates `message` unsigned with `blockhash` and returns the compute units it consumed
+pub fn simulate_units(
+    message: Message,
+    blockhash: Hash,
+    sender: &mut dyn Sender,
+    send_options: &SendOptions,
+) -> Result<u64> {
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.message.recent_blockhash = blockhash;
+    let simulation = sender.simulate(&transaction, send_options)?;
+    if let Some(err) = simulation.err {
+        bail!("simulation failed: {}", err);
+    }
+    if simulation.units_consumed == 0 {
+        bail!("the simulation logged no program invocations, set a number of units instead");
+    }
+    Ok(simulation.units_consumed)
+}
+
+/// Sums the compute units reported by the top level program invocations in `logs`.
+/// Inner invocations are skipped because their units are included in their caller's. A
+/// top level invocation that reports no units is one of a builtin program, and counts as
+/// [`BUILTIN_INSTRUCTION_UNITS`].
+pub fn units_consumed(logs: &[String]) -> u64 {
+    let mut depth = 0usize;
+    let mut units = 0;
+    let mut reported = false;
+    for log in logs {
+        let words = log.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["Program", _, "invoke", level] => {
+                depth = level
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .parse()
+                    .unwrap_or(depth + 1);
+                if depth == 1 {
+                    reported = false;
+                }
+            }
+            ["Program", _, "consumed", consumed, "of", _, "compute", "units"] if depth == 1 => {
+                units += consumed.parse::<u64>().unwrap_or_default();
+                reported = true;
+            }
+            ["Program", _, "success"] | ["Program", _, "failed:", ..] => {
+                if depth == 1 && !reported {
+                    units += BUILTIN_INSTRUCTION_UNITS;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+    units
+}
+
+/// The logs of the preflight simulation that rejected a transaction, if that is what
+/// `err` is about
+pub fn preflight_logs(err: &anyhow::Error) -> Vec<String> {
+    preflight_failure(err)
+        .and_then(|simulation| simulation.logs.clone())
+        .unwrap_or_default()
+}
+
+/// The preflight simulation that rejected a transaction, if that is what `err` is about
+fn preflight_failure(err: &anyhow::Error) -> Option<&RpcSimulateTransactionResult> {
+    match err.downcast_ref::<ClientError>().map(ClientError::kind) {
+        Some(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+            ..
+        })) => Some(simulation),
+        _ => None,
+    }
+}
+
+/// The error a transaction failed with on chain, if that is what `err` is about
+fn transaction_error(err: &anyhow::Error) -> Option<TransactionError> {
+    // send_with_retries passes on the status of an earlier attempt as it is
+    if let Some(err) = err.downcast_ref::<TransactionError>() {
+        return Some(err.clone());
+    }
+    match err.downcast_ref::<ClientError>().map(ClientError::kind) {
+        Some(ClientErrorKind::TransactionError(err)) => Some(err.clone()),
+        _ => None,
+    }
+}
+
+/// Fetches a confirmed transaction with its status by its signature
+pub fn fetch_confirmed_transaction(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    send_options: &SendOptions,
+) -> Result<EncodedConfirmedTransaction> {
+    // the cluster only serves transactions that are at least confirmed
+    let mut commitment = send_options.commitment_config();
+    if !commitment.is_at_least_confirmed() {
+        commitment = CommitmentConfig::confirmed();
+    }
+    let config = RpcConfirmedTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+    };
+    rpc_client
+        .get_confirmed_transaction_with_config(signature, config)
+        .with_context(|| format!("failed to fetch transaction {}", signature))
+}
+
+/// Sends `transaction` like [`send_expecting`] and reports how it went, with the slot,
+/// fee and logs of a confirmed transaction and the error and logs of a failed one.
+/// `report_attempt` is called with each attempt to send it.
+pub fn send_with_report(
+    transaction: &mut Transaction,
+    document: &Document,
+    sender: &mut dyn Sender,
+    rpc_client: &RpcClient,
+    send_options: &SendOptions,
+    resign: &mut dyn FnMut(Hash) -> Result<Transaction>,
+    report_attempt: &mut dyn FnMut(&Attempt),
+) -> (Report, Result<Signature>) {
+    let mut attempts = 0;
+    let result = send_expecting(
+        sender,
+        transaction,
+        document,
+        send_options,
+        resign,
+        &mut |attempt| {
+            attempts = attempt.number;
+            report_attempt(attempt);
+        },
+    );
+    let mut report = Report::new(transaction, Status::Sent);
+    report.attempts = attempts;
+    match &result {
+        Ok(_) if send_options.no_wait.unwrap_or_default() => {}
+        Ok(signature) => {
+            report.status = Status::Confirmed;
+            // the report only lacks the slot and fee if the cluster cannot serve them yet
+            if let Ok(confirmed) = fetch_confirmed_transaction(rpc_client, signature, send_options)
+            {
+                report.set_confirmed(&confirmed);
+            }
+        }
+        Err(err) => {
+            report.status = Status::Failed;
+            report.error = Some(err.to_string());
+            report.logs = preflight_logs(err);
+            // a transaction that passed preflight may have failed on chain, with logs
+            if report.logs.is_empty() {
+                let signature = &transaction.signatures[0];
+                if let Ok(confirmed) =
+                    fetch_confirmed_transaction(rpc_client, signature, send_options)
+                {
+                    report.set_confirmed(&confirmed);
+                }
+            }
+        }
+    }
+    (report, result)
+}
+
+/// Fetches a confirmed transaction by its signature
+pub fn fetch_transaction(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    send_options: &SendOptions,
+) -> Result<Transaction> {
+    fetch_confirmed_transaction(rpc_client, signature, send_options)?
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("failed to decode transaction {}", signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_consumed_counts_top_level_invocations() {
+        let logs = [
+            "Program A invoke [1]",
+            "Program B invoke [2]",
+            "Program B consumed 300 of 199000 compute units",
+            "Program B success",
+            "Program A consumed 1000 of 200000 compute units",
+            "Program A success",
+            "Program C invoke [1]",
+            "Program log: hello",
+            "Program C consumed 250 of 199000 compute units",
+            "Program C failed: custom program error: 0x1",
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Program 11111111111111111111111111111111 success",
+        ]
+        .iter()
+        .map(|log| log.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(units_consumed(&logs), 1250 + BUILTIN_INSTRUCTION_UNITS);
+        assert_eq!(units_consumed(&[]), 0);
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let preflight_failure = |err| {
+            anyhow::Error::from(ClientError::from(RpcError::RpcResponseError {
+                code: -32002,
+                message: "Transaction simulation failed".to_string(),
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                    RpcSimulateTransactionResult { err, logs: None },
+                ),
+            }))
+        };
+        let unreachable = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        assert!(is_retryable(&ClientError::from(unreachable).into()));
+        assert!(is_retryable(
+            &ClientError::from(RpcError::ForUser("unable to confirm".to_string())).into()
+        ));
+        assert!(is_retryable(&preflight_failure(Some(
+            TransactionError::BlockhashNotFound
+        ))));
+        assert!(is_retryable(&preflight_failure(Some(
+            TransactionError::AlreadyProcessed
+        ))));
+
+        assert!(!is_retryable(&preflight_failure(Some(
+            TransactionError::InsufficientFundsForFee
+        ))));
+        assert!(!is_retryable(
+            &ClientError::from(TransactionError::AccountInUse).into()
+        ));
+        assert!(!is_retryable(&anyhow!("failed to sign")));
+    }
+}
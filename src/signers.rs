@@ -0,0 +1,133 @@
+//! Loads signers from their signer sources like the solana cli, with the additions of
+//! `--signer`: bare pubkeys, `PUBKEY=SIGNATURE` pairs and `prompt://`
+
+use {
+    anyhow::{anyhow, bail, Result},
+    clap::ArgMatches,
+    solana_clap_utils::{
+        input_validators::{is_pubkey_sig, is_valid_signer},
+        keypair::{presigner_from_pubkey_sigs, signer_from_path},
+    },
+    solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{NullSigner, Presigner, Signature, Signer},
+    },
+    std::{str::FromStr, sync::Arc},
+};
+
+/// `prompt://` is accepted as an alias of the `ask:` scheme understood by solana-clap-utils
+pub fn normalize_signer_source(source: &str) -> String {
+    match source.strip_prefix("prompt:") {
+        Some(rest) => format!("ask:{}", rest),
+        None => source.to_string(),
+    }
+}
+
+/// Validates a `--signer`, which is either a signer source or a `PUBKEY=SIGNATURE` pair
+pub fn is_valid_signer_source(source: String) -> Result<(), String> {
+    if is_pubkey_sig(&source).is_ok() {
+        return Ok(());
+    }
+    is_valid_signer(normalize_signer_source(&source))
+}
+
+/// The `PUBKEY=SIGNATURE` pairs passed with `--signer`
+pub fn presigners(matches: &ArgMatches) -> Vec<(Pubkey, Signature)> {
+    matches
+        .values_of("signer")
+        .into_iter()
+        .flatten()
+        .filter(|value| is_pubkey_sig(value).is_ok())
+        .filter_map(|value| {
+            let mut pair = value.splitn(2, '=');
+            let pubkey = pair.next()?.parse().ok()?;
+            let signature = pair.next()?.parse().ok()?;
+            Some((pubkey, signature))
+        })
+        .collect()
+}
+
+/// The sources passed with `--signer`, where a `PUBKEY=SIGNATURE` pair stands for its
+/// pubkey
+pub fn signer_sources(matches: &ArgMatches) -> Vec<String> {
+    let presigned = presigners(matches)
+        .into_iter()
+        .map(|(pubkey, _)| pubkey.to_string());
+    let sources = matches
+        .values_of("signer")
+        .into_iter()
+        .flatten()
+        .filter(|value| is_pubkey_sig(value).is_err())
+        .map(str::to_string);
+    presigned.chain(sources).collect()
+}
+
+/// Resolves a signer source like solana-clap-utils' `signer_from_path`.
+///
+/// Bare pubkeys are handled here because `--signer` also takes keypair paths, which
+/// solana-clap-utils would try to read as `PUBKEY=SIGNATURE` pairs. A pubkey resolves to
+/// its presigner or, when only signing, to a `NullSigner`.
+pub fn load_signer(
+    source: &str,
+    keypair_name: &str,
+    sign_only: bool,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<Box<dyn Signer>> {
+    let source = normalize_signer_source(source);
+    if let Ok(pubkey) = Pubkey::from_str(&source) {
+        if let Some(presigner) = presigner_from_pubkey_sigs(&pubkey, &presigners(matches)) {
+            return Ok(Box::new(presigner));
+        }
+        if sign_only {
+            return Ok(Box::new(NullSigner::new(&pubkey)));
+        }
+        bail!(
+            "missing signature for {}, pass --signer {}=SIGNATURE",
+            pubkey,
+            pubkey
+        );
+    }
+    signer_from_path(matches, &source, keypair_name, wallet_manager)
+        .map_err(|err| anyhow!("failed to load {} {}: {}", keypair_name, source, err))
+}
+
+/// Resolves `source` and adds it to `signers` unless a signer for the same pubkey is
+/// already present. Returns the pubkey of the resolved signer.
+pub fn add_signer(
+    signers: &mut Vec<Box<dyn Signer>>,
+    source: &str,
+    sign_only: bool,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<Pubkey> {
+    let signer = load_signer(source, "signer", sign_only, matches, wallet_manager)?;
+    let pubkey = signer.pubkey();
+    add_unique(signers, signer);
+    Ok(pubkey)
+}
+
+/// Adds the signers passed with `--signer`, keypairs and `PUBKEY=SIGNATURE` pairs alike
+pub fn add_cli_signers(
+    signers: &mut Vec<Box<dyn Signer>>,
+    sign_only: bool,
+    matches: &ArgMatches,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<()> {
+    for (pubkey, signature) in presigners(matches) {
+        add_unique(signers, Box::new(Presigner::new(&pubkey, &signature)));
+    }
+    for source in matches.values_of("signer").into_iter().flatten() {
+        if is_pubkey_sig(source).is_err() {
+            add_signer(signers, source, sign_only, matches, wallet_manager)?;
+        }
+    }
+    Ok(())
+}
+
+fn add_unique(signers: &mut Vec<Box<dyn Signer>>, signer: Box<dyn Signer>) {
+    if !signers.iter().any(|s| s.pubkey() == signer.pubkey()) {
+        signers.push(signer);
+    }
+}
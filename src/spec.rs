@@ -0,0 +1,580 @@
+//! The serde model of transaction files.
+//!
+//! Documents are deserialized from their nodes with [`from_node`](crate::yaml::from_node),
+//! so errors point at a line and column like those of the rest of the parser. Raw
+//! instructions, their accounts, addresses and data are typed down to their bytes.
+//! Instruction shorthands, Anchor instructions and the `nonce`, `expect`, `programs` and
+//! `fixtures` sections keep their nodes, which their own parsers read. `soltx decode`
+//! prints transactions in the same model.
+
+use {
+    crate::{
+        error::ParseErrorKind,
+        parse::{parse_commitment, parse_compute_unit_limit, ComputeUnitLimit},
+        yaml::{one_of, Node},
+    },
+    serde::{
+        de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor},
+        ser::{SerializeMap, Serializer},
+        Deserialize, Serialize,
+    },
+    solana_sdk::{
+        commitment_config::CommitmentLevel,
+        instruction::{AccountMeta, Instruction},
+        pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN},
+        transaction::Transaction,
+    },
+    spl_associated_token_account::get_associated_token_address,
+    std::{any::type_name, fmt, marker::PhantomData, str::FromStr},
+};
+
+const FIELD_TYPES: &[&str] = &[
+    "hex", "base58", "base64", "utf8", "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32",
+    "i64", "i128", "bool", "pubkey", "string", "bump",
+];
+const ADDRESS_KINDS: &[&str] = &["pda", "ata"];
+
+/// A document written as a mapping. A document may also be a bare sequence of
+/// instructions.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DocumentSpec {
+    pub vars: Option<Node>,
+    pub accounts: Option<Node>,
+    /// Raw instructions, which are read as [`InstructionSpec`], shorthands and Anchor
+    /// instructions. A document without them only declares variables.
+    pub instructions: Option<Vec<Node>>,
+    #[serde(default, deserialize_with = "commitment")]
+    pub commitment: Option<CommitmentLevel>,
+    pub skip_preflight: Option<bool>,
+    #[serde(default, deserialize_with = "commitment")]
+    pub preflight_commitment: Option<CommitmentLevel>,
+    pub no_wait: Option<bool>,
+    #[serde(default, deserialize_with = "number")]
+    pub max_attempts: Option<u32>,
+    #[serde(default, deserialize_with = "number")]
+    pub retry_backoff: Option<u64>,
+    pub nonce: Option<Node>,
+    /// Signer source of the fee payer, like a keypair path or a pubkey
+    pub fee_payer: Option<String>,
+    #[serde(default, deserialize_with = "compute_unit_limit")]
+    pub compute_unit_limit: Option<ComputeUnitLimit>,
+    #[serde(default, deserialize_with = "number")]
+    pub compute_unit_price: Option<u64>,
+    pub programs: Option<Node>,
+    pub fixtures: Option<Node>,
+    pub expect: Option<Node>,
+}
+
+/// Transactions in the json and toml layout, as `soltx decode` prints them
+#[derive(Debug, Serialize)]
+pub struct TxFileSpec {
+    pub documents: Vec<TransactionSpec>,
+}
+
+/// The raw instructions of a transaction
+#[derive(Debug, Serialize)]
+pub struct TransactionSpec {
+    pub instructions: Vec<InstructionSpec>,
+}
+
+/// A raw instruction, `{programId, accounts, data}`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InstructionSpec {
+    pub program_id: AddressSpec,
+    pub accounts: Vec<AccountSpec>,
+    pub data: DataSpec,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountSpec {
+    pub key: AddressSpec,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    /// Source of the account's signer, like a keypair path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+}
+
+/// A pubkey, written in base58 or derived with `{pda: {program, seeds}}` or
+/// `{ata: {wallet, mint}}`. It is always written back in base58.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AddressSpec(pub Pubkey);
+
+/// The seeds of a program derived address, each a typed field like those of instruction
+/// data
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PdaSpec {
+    pub program: AddressSpec,
+    pub seeds: Vec<SeedSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtaSpec {
+    pub wallet: AddressSpec,
+    pub mint: AddressSpec,
+}
+
+/// A seed of a program derived address, at most `MAX_SEED_LEN` bytes long
+#[derive(Debug)]
+pub struct SeedSpec(pub Vec<u8>);
+
+/// The bump seed of a program derived address, written as `{pda: {program, seeds}}`
+#[derive(Debug)]
+pub struct BumpSpec(pub u8);
+
+/// The data of an instruction. It is always written back as `{hex: ...}`.
+#[derive(Debug)]
+pub enum DataSpec {
+    /// Comma separated decimal bytes like `0,1,255`, or a single byte
+    Bytes(Vec<u8>),
+    /// A single field like `{hex: 00ff}`
+    Field(FieldSpec),
+    /// Typed fields, which are concatenated
+    Fields(Vec<FieldSpec>),
+}
+
+/// A single-entry mapping such as `{u64: 5}`.
+///
+/// The encodings `hex`, `base58`, `base64` and `utf8` hold their raw bytes. Typed fields
+/// are borsh encoded: integers are little-endian, a `bool` is one byte and a `string` is
+/// prefixed with its length as a `u32`.
+#[derive(Debug, PartialEq)]
+pub enum FieldSpec {
+    Hex(Vec<u8>),
+    Base58(Vec<u8>),
+    Base64(Vec<u8>),
+    Utf8(String),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    Bool(bool),
+    Pubkey(Pubkey),
+    String(String),
+    /// The bump seed of a `{pda: ...}` address
+    Bump(u8),
+}
+
+/// A number written as an integer or, for values that do not fit an `i64`, as a string
+pub struct Number<T>(pub T);
+
+impl InstructionSpec {
+    pub fn to_instruction(&self) -> Instruction {
+        Instruction {
+            program_id: self.program_id.0,
+            accounts: self
+                .accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.key.0,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: self.data.to_bytes(),
+        }
+    }
+}
+
+impl PdaSpec {
+    /// Finds the address and its bump seed
+    pub fn find_program_address(&self) -> Result<(Pubkey, u8), ParseErrorKind> {
+        // find_program_address appends the bump seed, which takes up one of the slots
+        if self.seeds.len() >= MAX_SEEDS {
+            return Err(ParseErrorKind::InvalidSeeds(format!(
+                "found {} seeds, at most {} are allowed",
+                self.seeds.len(),
+                MAX_SEEDS - 1
+            )));
+        }
+        let seeds = self
+            .seeds
+            .iter()
+            .map(|seed| seed.0.as_slice())
+            .collect::<Vec<_>>();
+        Pubkey::try_find_program_address(&seeds, &self.program.0)
+            .ok_or_else(|| ParseErrorKind::InvalidSeeds("no viable bump seed found".to_string()))
+    }
+}
+
+impl DataSpec {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            DataSpec::Bytes(bytes) => bytes.clone(),
+            DataSpec::Field(field) => field.to_bytes(),
+            DataSpec::Fields(fields) => fields.iter().flat_map(FieldSpec::to_bytes).collect(),
+        }
+    }
+}
+
+impl FieldSpec {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            FieldSpec::Hex(bytes) | FieldSpec::Base58(bytes) | FieldSpec::Base64(bytes) => {
+                bytes.clone()
+            }
+            FieldSpec::Utf8(string) => string.as_bytes().to_vec(),
+            FieldSpec::U8(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::U16(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::U32(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::U64(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::U128(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::I8(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::I16(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::I32(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::I64(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::I128(number) => number.to_le_bytes().to_vec(),
+            FieldSpec::Bool(b) => vec![*b as u8],
+            FieldSpec::Pubkey(pubkey) => pubkey.to_bytes().to_vec(),
+            FieldSpec::String(string) => {
+                let mut bytes = (string.len() as u32).to_le_bytes().to_vec();
+                bytes.extend(string.as_bytes());
+                bytes
+            }
+            FieldSpec::Bump(bump) => vec![*bump],
+        }
+    }
+}
+
+impl From<&Transaction> for TransactionSpec {
+    /// The signer and writable flags of each account come from the message header, so
+    /// the document compiles to the same message, fee payer aside. The fee payer is left
+    /// out, as a pubkey it would need `--signer PUBKEY=SIGNATURE` to be sent again.
+    fn from(transaction: &Transaction) -> Self {
+        let message = &transaction.message;
+        let instructions = message
+            .instructions
+            .iter()
+            .map(|instruction| InstructionSpec {
+                program_id: AddressSpec(
+                    message.account_keys[instruction.program_id_index as usize],
+                ),
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| {
+                        let index = *index as usize;
+                        AccountSpec {
+                            key: AddressSpec(message.account_keys[index]),
+                            is_signer: message.is_signer(index),
+                            is_writable: message.is_writable(index, false),
+                            signer: None,
+                        }
+                    })
+                    .collect(),
+                data: DataSpec::Bytes(instruction.data.clone()),
+            })
+            .collect();
+        TransactionSpec { instructions }
+    }
+}
+
+impl Serialize for AddressSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for AddressSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(AddressVisitor)
+    }
+}
+
+struct AddressVisitor;
+
+impl<'de> Visitor<'de> for AddressVisitor {
+    type Value = AddressSpec;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a base58 pubkey")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<AddressSpec, E> {
+        key.parse()
+            .map(AddressSpec)
+            .map_err(|_| E::custom(ParseErrorKind::InvalidPubkey(key.to_string())))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<AddressSpec, A::Error> {
+        let address = match tag(&mut map, ADDRESS_KINDS)?.as_str() {
+            "pda" => {
+                let (address, _bump) = map
+                    .next_value::<PdaSpec>()?
+                    .find_program_address()
+                    .map_err(de::Error::custom)?;
+                address
+            }
+            "ata" => {
+                let ata = map.next_value::<AtaSpec>()?;
+                get_associated_token_address(&ata.wallet.0, &ata.mint.0)
+            }
+            kind => return Err(de::Error::unknown_variant(kind, ADDRESS_KINDS)),
+        };
+        end(&mut map, ADDRESS_KINDS)?;
+        Ok(AddressSpec(address))
+    }
+}
+
+impl<'de> Deserialize<'de> for BumpSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(BumpVisitor)
+    }
+}
+
+struct BumpVisitor;
+
+impl<'de> Visitor<'de> for BumpVisitor {
+    type Value = BumpSpec;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a pda mapping")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<BumpSpec, A::Error> {
+        match tag(&mut map, &["pda"])?.as_str() {
+            "pda" => {
+                let (_address, bump) = map
+                    .next_value::<PdaSpec>()?
+                    .find_program_address()
+                    .map_err(de::Error::custom)?;
+                end(&mut map, &["pda"])?;
+                Ok(BumpSpec(bump))
+            }
+            kind => Err(de::Error::unknown_variant(kind, &["pda"])),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SeedSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = FieldSpec::deserialize(deserializer)?.to_bytes();
+        if bytes.len() > MAX_SEED_LEN {
+            return Err(de::Error::custom(ParseErrorKind::InvalidSeeds(format!(
+                "seed is {} bytes long, at most {} are allowed",
+                bytes.len(),
+                MAX_SEED_LEN
+            ))));
+        }
+        Ok(SeedSpec(bytes))
+    }
+}
+
+impl Serialize for DataSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("hex", &hex::encode(self.to_bytes()))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for DataSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DataVisitor)
+    }
+}
+
+struct DataVisitor;
+
+impl<'de> Visitor<'de> for DataVisitor {
+    type Value = DataSpec;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "comma separated bytes, a mapping or a sequence")
+    }
+
+    fn visit_i64<E: de::Error>(self, byte: i64) -> Result<DataSpec, E> {
+        self.visit_str(&byte.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, byte: u64) -> Result<DataSpec, E> {
+        self.visit_str(&byte.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, data: &str) -> Result<DataSpec, E> {
+        if data.trim().is_empty() {
+            return Ok(DataSpec::Bytes(vec![]));
+        }
+        data.split(',')
+            .map(|byte| {
+                let byte = byte.trim();
+                byte.parse::<u8>()
+                    .map_err(|_| E::custom(ParseErrorKind::InvalidByte(byte.to_string())))
+            })
+            .collect::<Result<_, _>>()
+            .map(DataSpec::Bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DataSpec, A::Error> {
+        let mut fields = vec![];
+        while let Some(field) = seq.next_element()? {
+            fields.push(field);
+        }
+        Ok(DataSpec::Fields(fields))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<DataSpec, A::Error> {
+        FieldVisitor.visit_map(map).map(DataSpec::Field)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(FieldVisitor)
+    }
+}
+
+struct FieldVisitor;
+
+impl<'de> Visitor<'de> for FieldVisitor {
+    type Value = FieldSpec;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a mapping")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldSpec, A::Error> {
+        let field = match tag(&mut map, FIELD_TYPES)?.as_str() {
+            "hex" => FieldSpec::Hex(
+                map.next_value_seed(Encoded(|s| hex::decode(s).map_err(|err| err.to_string())))?,
+            ),
+            "base58" => FieldSpec::Base58(map.next_value_seed(Encoded(|s| {
+                bs58::decode(s).into_vec().map_err(|err| err.to_string())
+            }))?),
+            "base64" => FieldSpec::Base64(map.next_value_seed(Encoded(|s| {
+                base64::decode(s).map_err(|err| err.to_string())
+            }))?),
+            "utf8" => FieldSpec::Utf8(map.next_value()?),
+            "u8" => FieldSpec::U8(map.next_value::<Number<_>>()?.0),
+            "u16" => FieldSpec::U16(map.next_value::<Number<_>>()?.0),
+            "u32" => FieldSpec::U32(map.next_value::<Number<_>>()?.0),
+            "u64" => FieldSpec::U64(map.next_value::<Number<_>>()?.0),
+            "u128" => FieldSpec::U128(map.next_value::<Number<_>>()?.0),
+            "i8" => FieldSpec::I8(map.next_value::<Number<_>>()?.0),
+            "i16" => FieldSpec::I16(map.next_value::<Number<_>>()?.0),
+            "i32" => FieldSpec::I32(map.next_value::<Number<_>>()?.0),
+            "i64" => FieldSpec::I64(map.next_value::<Number<_>>()?.0),
+            "i128" => FieldSpec::I128(map.next_value::<Number<_>>()?.0),
+            "bool" => FieldSpec::Bool(map.next_value()?),
+            "pubkey" => FieldSpec::Pubkey(map.next_value::<AddressSpec>()?.0),
+            "string" => FieldSpec::String(map.next_value()?),
+            "bump" => FieldSpec::Bump(map.next_value::<BumpSpec>()?.0),
+            tag => return Err(de::Error::unknown_variant(tag, FIELD_TYPES)),
+        };
+        end(&mut map, FIELD_TYPES)?;
+        Ok(field)
+    }
+}
+
+/// Decodes a string with the function it holds
+struct Encoded(fn(&str) -> Result<Vec<u8>, String>);
+
+impl<'de> DeserializeSeed<'de> for Encoded {
+    type Value = Vec<u8>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        (self.0)(&encoded).map_err(|err| de::Error::custom(ParseErrorKind::InvalidEncoding(err)))
+    }
+}
+
+impl<'de, T: FromStr> Deserialize<'de> for Number<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberVisitor(PhantomData))
+    }
+}
+
+struct NumberVisitor<T>(PhantomData<T>);
+
+impl<T: FromStr> NumberVisitor<T> {
+    fn parse<E: de::Error>(number: &str) -> Result<Number<T>, E> {
+        number
+            .parse()
+            .map(Number)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(number), &type_name::<T>()))
+    }
+}
+
+impl<'de, T: FromStr> Visitor<'de> for NumberVisitor<T> {
+    type Value = Number<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number")
+    }
+
+    fn visit_i64<E: de::Error>(self, number: i64) -> Result<Number<T>, E> {
+        Self::parse(&number.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, number: u64) -> Result<Number<T>, E> {
+        Self::parse(&number.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, number: &str) -> Result<Number<T>, E> {
+        Self::parse(number.trim())
+    }
+}
+
+/// Reads the tag of a single-entry mapping like `{u64: 5}`, whose value is read next
+fn tag<'de, A: MapAccess<'de>>(
+    map: &mut A,
+    expected: &'static [&'static str],
+) -> Result<String, A::Error> {
+    map.next_key()?
+        .ok_or_else(|| de::Error::custom(ParseErrorKind::ExpectedTagged(one_of(expected))))
+}
+
+/// Fails if a mapping read with [`tag`] has more entries
+fn end<'de, A: MapAccess<'de>>(
+    map: &mut A,
+    expected: &'static [&'static str],
+) -> Result<(), A::Error> {
+    match map.next_key::<IgnoredAny>()? {
+        Some(_) => Err(de::Error::custom(ParseErrorKind::ExpectedTagged(one_of(
+            expected,
+        )))),
+        None => Ok(()),
+    }
+}
+
+fn number<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<Option<T>, D::Error> {
+    Number::deserialize(deserializer).map(|number| Some(number.0))
+}
+
+/// `processed`, `confirmed` or `finalized`
+fn commitment<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<CommitmentLevel>, D::Error> {
+    let commitment = String::deserialize(deserializer)?;
+    parse_commitment(&commitment).map(Some).ok_or_else(|| {
+        de::Error::invalid_value(
+            de::Unexpected::Str(&commitment),
+            &"commitment, one of processed, confirmed or finalized",
+        )
+    })
+}
+
+/// `auto` or a number of compute units
+fn compute_unit_limit<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<ComputeUnitLimit>, D::Error> {
+    // a String parses from any number
+    let Number(limit) = Number::<String>::deserialize(deserializer)?;
+    parse_compute_unit_limit(&limit).map(Some).ok_or_else(|| {
+        de::Error::invalid_value(
+            de::Unexpected::Str(&limit),
+            &"a number of compute units or auto",
+        )
+    })
+}
@@ -0,0 +1,182 @@
+use {
+    crate::{
+        error::{ParseError, ParseErrorKind},
+        yaml::{Node, Value},
+    },
+    std::collections::BTreeMap,
+    yaml_rust::Yaml,
+};
+
+/// Named values declared in `vars:`/`accounts:` sections and referenced as `$name`.
+/// A string that starts with `$$` is not a reference but the string with its first `$`
+/// dropped, so `$$5 tip` is written for `$5 tip`.
+///
+/// Values passed on the command line with `--set` take precedence over the file, so the
+/// same file can be pointed at different deployments.
+#[derive(Default)]
+pub struct Variables {
+    values: BTreeMap<String, Node>,
+    overrides: BTreeMap<String, Node>,
+}
+
+impl Variables {
+    /// Creates variables from `NAME=VALUE` assignments given on the command line
+    pub fn with_overrides<'a>(assignments: impl IntoIterator<Item = &'a str>) -> Self {
+        let overrides = assignments
+            .into_iter()
+            .filter_map(|assignment| {
+                let (name, value) = split_assignment(assignment)?;
+                let node = Node {
+                    value: Value::Scalar(Yaml::from_str(value)),
+                    position: Default::default(),
+                };
+                Some((name.to_string(), node))
+            })
+            .collect();
+        Self {
+            values: BTreeMap::new(),
+            overrides,
+        }
+    }
+
+    /// Declares the variables of a `vars:` or `accounts:` section. Values may reference
+    /// variables declared before them.
+    pub fn declare(&mut self, section: &Node, path: &str) -> Result<(), ParseError> {
+        let entries = section
+            .as_mapping()
+            .ok_or_else(|| ParseError::invalid_type(section, path, "a mapping"))?;
+        for (name, value) in entries {
+            let name = name
+                .as_str()
+                .ok_or_else(|| ParseError::invalid_type(name, path, "a variable name"))?;
+            let value = self.substitute(value, &join(path, name))?;
+            self.values.insert(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.overrides.get(name).or_else(|| self.values.get(name))
+    }
+
+    /// Returns a copy of `node` with every `$name` scalar replaced by the variable's value
+    pub fn substitute(&self, node: &Node, path: &str) -> Result<Node, ParseError> {
+        let value = match &node.value {
+            Value::Scalar(Yaml::String(s)) if s.starts_with("$$") => {
+                Value::Scalar(Yaml::String(s[1..].to_string()))
+            }
+            Value::Scalar(Yaml::String(s)) if s.starts_with('$') => {
+                let name = &s[1..];
+                let variable = self.get(name).ok_or_else(|| {
+                    ParseError::new(
+                        node,
+                        path,
+                        ParseErrorKind::UndefinedVariable(name.to_string()),
+                    )
+                })?;
+                variable.value.clone()
+            }
+            Value::Scalar(scalar) => Value::Scalar(scalar.clone()),
+            Value::Sequence(nodes) => Value::Sequence(
+                nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, node)| self.substitute(node, &format!("{}[{}]", path, index)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Mapping(entries) => Value::Mapping(
+                entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let path = join(path, key.as_str().unwrap_or_default());
+                        Ok((key.clone(), self.substitute(value, &path)?))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        // keep the position of the reference so errors point at where the value is used
+        Ok(Node {
+            value,
+            position: node.position,
+        })
+    }
+}
+
+/// Splits `NAME=VALUE`
+pub fn split_assignment(assignment: &str) -> Option<(&str, &str)> {
+    let mut parts = assignment.splitn(2, '=');
+    let name = parts.next().filter(|name| !name.is_empty())?;
+    Some((name, parts.next()?))
+}
+
+pub fn is_assignment(assignment: String) -> Result<(), String> {
+    split_assignment(&assignment)
+        .map(|_| ())
+        .ok_or_else(|| format!("expected NAME=VALUE, found {}", assignment))
+}
+
+/// Appends a mapping key to a yaml path
+pub fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::yaml::load_from_str};
+
+    fn node(source: &str) -> Node {
+        load_from_str(source).unwrap().remove(0)
+    }
+
+    #[test]
+    fn references_are_substituted() {
+        let mut vars = Variables::default();
+        vars.declare(&node("amount: 5\ntotal: $amount"), "vars")
+            .unwrap();
+        let substituted = vars
+            .substitute(&node("{lamports: $total, memo: [$amount]}"), "")
+            .unwrap();
+        assert_eq!(substituted.get("lamports").unwrap().as_i64(), Some(5));
+        assert_eq!(
+            substituted.get("memo").unwrap().as_sequence().unwrap()[0].as_i64(),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let mut vars = Variables::with_overrides(vec!["amount=7", "memo=a=b"]);
+        vars.declare(&node("amount: 5\nmemo: x"), "vars").unwrap();
+        assert_eq!(vars.get("amount").unwrap().as_i64(), Some(7));
+        assert_eq!(vars.get("memo").unwrap().as_str(), Some("a=b"));
+        assert!(vars.get("missing").is_none());
+    }
+
+    #[test]
+    fn double_dollar_escapes() {
+        let vars = Variables::default();
+        let substituted = vars.substitute(&node("$$5 tip"), "").unwrap();
+        assert_eq!(substituted.as_str(), Some("$5 tip"));
+    }
+
+    #[test]
+    fn undefined_variables() {
+        let err = Variables::default()
+            .substitute(&node("to: $bob"), "")
+            .unwrap_err();
+        assert_eq!(err.path, "to");
+        assert_eq!((err.line, err.column), (1, 5));
+        assert!(matches!(err.kind, ParseErrorKind::UndefinedVariable(name) if name == "bob"));
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(split_assignment("a=b=c"), Some(("a", "b=c")));
+        assert_eq!(split_assignment("=b"), None);
+        assert_eq!(split_assignment("a"), None);
+    }
+}
@@ -0,0 +1,738 @@
+use {
+    crate::{
+        error::{ParseError, ParseErrorKind},
+        vars::join,
+    },
+    serde::de::{
+        self, value::StrDeserializer, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer,
+        EnumAccess, Expected, IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess,
+        Visitor,
+    },
+    std::{collections::BTreeMap, fmt},
+    yaml_rust::{
+        parser::{Event, MarkedEventReceiver, Parser},
+        scanner::{Marker, ScanError, TScalarStyle},
+        Yaml,
+    },
+};
+
+/// A yaml node that remembers where it was found in the source.
+///
+/// `YamlLoader` throws the parser's markers away, so soltx builds its own tree in order
+/// to point parse errors at a line and column.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub value: Value,
+    pub position: Position,
+}
+
+/// 1-based line and column of a node in its source
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<Marker> for Position {
+    fn from(marker: Marker) -> Self {
+        Self {
+            line: marker.line(),
+            column: marker.col() + 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    /// A resolved scalar, always one of the scalar variants of `Yaml`
+    Scalar(Yaml),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+impl Node {
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::Scalar(Yaml::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value {
+            Value::Scalar(Yaml::Boolean(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value {
+            Value::Scalar(Yaml::Integer(i)) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match &self.value {
+            Value::Scalar(Yaml::Real(real)) => real.parse().ok(),
+            Value::Scalar(Yaml::Integer(i)) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence(&self) -> Option<&[Node]> {
+        match &self.value {
+            Value::Sequence(nodes) => Some(nodes),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping(&self) -> Option<&[(Node, Node)]> {
+        match &self.value {
+            Value::Mapping(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this node is a mapping
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.as_mapping()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.value, Value::Scalar(Yaml::Null))
+    }
+
+    /// A short description of the node's type for error messages
+    pub fn type_name(&self) -> &'static str {
+        match &self.value {
+            Value::Scalar(Yaml::String(_)) => "a string",
+            Value::Scalar(Yaml::Integer(_)) => "an integer",
+            Value::Scalar(Yaml::Real(_)) => "a float",
+            Value::Scalar(Yaml::Boolean(_)) => "a boolean",
+            Value::Scalar(Yaml::Null) => "null",
+            Value::Scalar(_) => "an invalid value",
+            Value::Sequence(_) => "a sequence",
+            Value::Mapping(_) => "a mapping",
+        }
+    }
+}
+
+/// The name under which [`NodeDeserializer`] recognizes a `Node` field of the serde model,
+/// which it hands over with its position
+const NODE: &str = "$soltx::Node";
+
+/// Builds a node from any serde format, which is how json and toml files are read. Such
+/// nodes have no position, except when they are deserialized from another node.
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NODE, NodeVisitor)
+    }
+}
+
+/// Deserializes a node of any shape, without asking for its position
+struct AnyNode;
+
+impl<'de> DeserializeSeed<'de> for AnyNode {
+    type Value = Node;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl NodeVisitor {
+    fn node(value: Value) -> Node {
+        Node {
+            value,
+            position: Position::default(),
+        }
+    }
+
+    fn scalar(yaml: Yaml) -> Node {
+        Self::node(Value::Scalar(yaml))
+    }
+}
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::Boolean(b)))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::Integer(i)))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<Node, E> {
+        // numbers beyond an i64 are accepted as strings wherever numbers are expected
+        Ok(Self::scalar(match u as i64 {
+            i if i >= 0 => Yaml::Integer(i),
+            _ => Yaml::String(u.to_string()),
+        }))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::Real(f.to_string())))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::String(s.to_string())))
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::Null))
+    }
+
+    fn visit_none<E>(self) -> Result<Node, E> {
+        Ok(Self::scalar(Yaml::Null))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        Node::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut nodes = vec![];
+        while let Some(node) = seq.next_element()? {
+            nodes.push(node);
+        }
+        Ok(Self::node(Value::Sequence(nodes)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = vec![];
+        while let Some(key) = map.next_key::<Node>()? {
+            // a node handed over by `LocatedNode`
+            if entries.is_empty() && key.as_str() == Some(LINE) {
+                let line = map.next_value()?;
+                map.next_key::<Node>()?;
+                let column = map.next_value()?;
+                map.next_key::<Node>()?;
+                let mut node = map.next_value_seed(AnyNode)?;
+                node.position = Position { line, column };
+                return Ok(node);
+            }
+            entries.push((key, map.next_value()?));
+        }
+        Ok(Self::node(Value::Mapping(entries)))
+    }
+}
+
+/// Deserializes `node` into a type of the serde model, locating errors like the parser
+/// does, at the path and position of the offending node
+pub fn from_node<T: DeserializeOwned>(node: &Node, path: &str) -> Result<T, ParseError> {
+    T::deserialize(NodeDeserializer {
+        node,
+        path: path.to_string(),
+    })
+    .map_err(|err| err.locate(node, path))
+    .map_err(|err| match err {
+        DeError::Located(err) => err,
+        // unreachable, located right above
+        DeError::Unlocated(kind) => ParseError::new(node, path, kind),
+    })
+}
+
+/// The keys under which `LocatedNode` hands over a node with its position
+const LINE: &str = "$soltx::line";
+const COLUMN: &str = "$soltx::column";
+const VALUE: &str = "$soltx::value";
+
+/// An error of [`NodeDeserializer`]. It is raised without a location and located by the
+/// deserializer of the node it is about on its way out.
+#[derive(Debug)]
+enum DeError {
+    Unlocated(ParseErrorKind),
+    Located(ParseError),
+}
+
+impl DeError {
+    fn locate(self, node: &Node, path: &str) -> Self {
+        match self {
+            DeError::Unlocated(kind) => DeError::Located(ParseError::new(node, path, kind)),
+            located => located,
+        }
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeError::Unlocated(kind) => write!(f, "{}", kind),
+            DeError::Located(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        DeError::Unlocated(ParseErrorKind::Custom(message.to_string()))
+    }
+
+    fn invalid_type(unexpected: Unexpected, expected: &dyn Expected) -> Self {
+        DeError::Unlocated(ParseErrorKind::InvalidType {
+            expected: expected.to_string(),
+            found: match unexpected {
+                Unexpected::Bool(_) => "a boolean",
+                Unexpected::Unsigned(_) | Unexpected::Signed(_) => "an integer",
+                Unexpected::Float(_) => "a float",
+                Unexpected::Str(_) | Unexpected::Char(_) => "a string",
+                Unexpected::Unit | Unexpected::Option => "null",
+                Unexpected::Seq => "a sequence",
+                Unexpected::Map => "a mapping",
+                _ => "an invalid value",
+            },
+        })
+    }
+
+    fn invalid_value(unexpected: Unexpected, expected: &dyn Expected) -> Self {
+        let value = match unexpected {
+            Unexpected::Bool(b) => b.to_string(),
+            Unexpected::Unsigned(u) => u.to_string(),
+            Unexpected::Signed(i) => i.to_string(),
+            Unexpected::Float(f) => f.to_string(),
+            Unexpected::Str(s) => s.to_string(),
+            unexpected => unexpected.to_string(),
+        };
+        DeError::Unlocated(ParseErrorKind::InvalidValue {
+            value,
+            expected: expected.to_string(),
+        })
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        DeError::Unlocated(ParseErrorKind::UnknownTag {
+            tag: variant.to_string(),
+            expected: one_of(expected),
+        })
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        DeError::Unlocated(ParseErrorKind::UnknownField {
+            field: field.to_string(),
+            expected: expected.join(", "),
+        })
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        DeError::Unlocated(ParseErrorKind::MissingField(field))
+    }
+}
+
+/// Lists `names` like `a, b or c`
+pub fn one_of(names: &[&str]) -> String {
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Deserializes the serde model from a node, see [`from_node`]
+#[derive(Clone)]
+struct NodeDeserializer<'a> {
+    node: &'a Node,
+    path: String,
+}
+
+impl<'a> NodeDeserializer<'a> {
+    fn child(&self, node: &'a Node, path: String) -> Self {
+        NodeDeserializer { node, path }
+    }
+
+    fn invalid_type(&self, expected: &str) -> DeError {
+        DeError::Located(ParseError::new(
+            self.node,
+            &self.path,
+            ParseErrorKind::InvalidType {
+                expected: expected.to_string(),
+                found: self.node.type_name(),
+            },
+        ))
+    }
+
+    fn locate<T>(&self, result: Result<T, DeError>) -> Result<T, DeError> {
+        result.map_err(|err| err.locate(self.node, &self.path))
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for NodeDeserializer<'a> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let result = match &self.node.value {
+            Value::Scalar(Yaml::String(s)) => visitor.visit_str(s),
+            Value::Scalar(Yaml::Integer(i)) => visitor.visit_i64(*i),
+            Value::Scalar(Yaml::Boolean(b)) => visitor.visit_bool(*b),
+            Value::Scalar(Yaml::Null) => visitor.visit_unit(),
+            Value::Scalar(Yaml::Real(_)) => match self.node.as_f64() {
+                Some(f) => visitor.visit_f64(f),
+                None => return Err(self.invalid_type("a valid value")),
+            },
+            Value::Scalar(_) => return Err(self.invalid_type("a valid value")),
+            Value::Sequence(nodes) => visitor.visit_seq(Sequence {
+                parent: &self,
+                nodes: nodes.iter().enumerate(),
+            }),
+            Value::Mapping(entries) => visitor.visit_map(Mapping {
+                parent: &self,
+                entries: entries.iter(),
+                value: None,
+            }),
+        };
+        self.locate(result)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.node.as_bool() {
+            Some(b) => self.locate(visitor.visit_bool(b)),
+            None => Err(self.invalid_type("a boolean")),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.node.as_str() {
+            Some(s) => self.locate(visitor.visit_str(s)),
+            None => Err(self.invalid_type("a string")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.node.is_null() {
+            self.locate(visitor.visit_none())
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        if name == NODE {
+            return visitor.visit_map(LocatedNode {
+                parent: self,
+                next: 0,
+            });
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.node.value {
+            Value::Sequence(_) => self.deserialize_any(visitor),
+            _ => Err(self.invalid_type("a sequence")),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.node.value {
+            Value::Mapping(_) => self.deserialize_any(visitor),
+            _ => Err(self.invalid_type("a mapping")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Enums are externally tagged, written as a single-entry mapping like `{u64: 5}`
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.node.as_mapping() {
+            Some([(tag, value)]) => {
+                let result = visitor.visit_enum(Tagged {
+                    parent: &self,
+                    tag,
+                    value,
+                });
+                self.locate(result)
+            }
+            Some(_) => Err(DeError::Located(ParseError::new(
+                self.node,
+                &self.path,
+                ParseErrorKind::ExpectedTagged(one_of(variants)),
+            ))),
+            None => Err(self.invalid_type("a mapping")),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf unit unit_struct
+        tuple tuple_struct ignored_any
+    }
+}
+
+struct Sequence<'p, 'a> {
+    parent: &'p NodeDeserializer<'a>,
+    nodes: std::iter::Enumerate<std::slice::Iter<'a, Node>>,
+}
+
+impl<'de> SeqAccess<'de> for Sequence<'_, '_> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        match self.nodes.next() {
+            Some((index, node)) => {
+                let path = format!("{}[{}]", self.parent.path, index);
+                let child = self.parent.child(node, path);
+                child.locate(seed.deserialize(child.clone())).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+struct Mapping<'p, 'a> {
+    parent: &'p NodeDeserializer<'a>,
+    entries: std::slice::Iter<'a, (Node, Node)>,
+    /// The value of the key that was read last, with its path
+    value: Option<(&'a Node, String)>,
+}
+
+impl<'de> MapAccess<'de> for Mapping<'_, '_> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                let path = join(&self.parent.path, key.as_str().unwrap_or_default());
+                let child = self.parent.child(key, path.clone());
+                let key = child.locate(seed.deserialize(child.clone()))?;
+                self.value = Some((value, path));
+                Ok(Some(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        // unwrap OK because serde reads a value only after its key
+        let (value, path) = self.value.take().unwrap();
+        let child = self.parent.child(value, path);
+        child.locate(seed.deserialize(child.clone()))
+    }
+}
+
+/// The single entry of an externally tagged enum
+struct Tagged<'p, 'a> {
+    parent: &'p NodeDeserializer<'a>,
+    tag: &'a Node,
+    value: &'a Node,
+}
+
+impl<'de, 'p, 'a> EnumAccess<'de> for Tagged<'p, 'a> {
+    type Error = DeError;
+    type Variant = NodeDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, NodeDeserializer<'a>), DeError> {
+        let tag = self.parent.child(self.tag, self.parent.path.clone());
+        let variant = tag.locate(seed.deserialize(tag.clone()))?;
+        let path = join(&self.parent.path, self.tag.as_str().unwrap_or_default());
+        Ok((variant, self.parent.child(self.value, path)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for NodeDeserializer<'_> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, DeError> {
+        self.locate(seed.deserialize(self.clone()))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+}
+
+/// Hands a node over to `NodeVisitor` with its position, as a mapping of its line, column
+/// and value
+struct LocatedNode<'a> {
+    parent: NodeDeserializer<'a>,
+    next: usize,
+}
+
+impl<'de> MapAccess<'de> for LocatedNode<'_> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        let key = match self.next {
+            0 => LINE,
+            1 => COLUMN,
+            2 => VALUE,
+            _ => return Ok(None),
+        };
+        let key: StrDeserializer<DeError> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let position = self.parent.node.position;
+        self.next += 1;
+        match self.next {
+            1 => seed.deserialize((position.line as u64).into_deserializer()),
+            2 => seed.deserialize((position.column as u64).into_deserializer()),
+            _ => seed.deserialize(
+                self.parent
+                    .child(self.parent.node, self.parent.path.clone()),
+            ),
+        }
+    }
+}
+
+/// Loads every document in `source`
+pub fn load_from_str(source: &str) -> Result<Vec<Node>, ScanError> {
+    let mut loader = Loader::default();
+    Parser::new(source.chars()).load(&mut loader, true)?;
+    Ok(loader.docs)
+}
+
+#[derive(Default)]
+struct Loader {
+    docs: Vec<Node>,
+    // (node under construction, anchor id)
+    stack: Vec<(Node, usize)>,
+    // pending key of each mapping on the stack
+    keys: Vec<Option<Node>>,
+    anchors: BTreeMap<usize, Node>,
+}
+
+impl Loader {
+    fn insert(&mut self, node: Node, anchor_id: usize) {
+        // valid anchor ids start from 1
+        if anchor_id > 0 {
+            self.anchors.insert(anchor_id, node.clone());
+        }
+        match self.stack.last_mut() {
+            None => self.docs.push(node),
+            Some((parent, _)) => match &mut parent.value {
+                Value::Sequence(nodes) => nodes.push(node),
+                Value::Mapping(entries) => {
+                    // unwrap OK because every mapping on the stack has a key slot
+                    let key = self.keys.last_mut().unwrap();
+                    match key.take() {
+                        None => *key = Some(node),
+                        Some(key) => entries.push((key, node)),
+                    }
+                }
+                Value::Scalar(_) => unreachable!(),
+            },
+        }
+    }
+
+    fn close(&mut self) {
+        // unwrap OK because the parser balances start and end events
+        let (node, anchor_id) = self.stack.pop().unwrap();
+        self.insert(node, anchor_id);
+    }
+}
+
+impl MarkedEventReceiver for Loader {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::SequenceStart(anchor_id) => self.stack.push((
+                Node {
+                    value: Value::Sequence(vec![]),
+                    position: marker.into(),
+                },
+                anchor_id,
+            )),
+            Event::MappingStart(anchor_id) => {
+                self.stack.push((
+                    Node {
+                        value: Value::Mapping(vec![]),
+                        position: marker.into(),
+                    },
+                    anchor_id,
+                ));
+                self.keys.push(None);
+            }
+            Event::SequenceEnd => self.close(),
+            Event::MappingEnd => {
+                self.keys.pop();
+                self.close();
+            }
+            Event::Scalar(value, style, anchor_id, _tag) => {
+                let value = if style == TScalarStyle::Plain {
+                    Yaml::from_str(&value)
+                } else {
+                    Yaml::String(value)
+                };
+                self.insert(
+                    Node {
+                        value: Value::Scalar(value),
+                        position: marker.into(),
+                    },
+                    anchor_id,
+                );
+            }
+            Event::Alias(anchor_id) => {
+                let node = self.anchors.get(&anchor_id).cloned().unwrap_or(Node {
+                    value: Value::Scalar(Yaml::BadValue),
+                    position: marker.into(),
+                });
+                self.insert(node, 0);
+            }
+            _ => {}
+        }
+    }
+}